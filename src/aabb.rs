@@ -0,0 +1,126 @@
+use crate::{
+    interval::{self, Interval},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+pub const EMPTY: Aabb = Aabb {
+    x: interval::EMPTY,
+    y: interval::EMPTY,
+    z: interval::EMPTY,
+};
+
+pub const UNIVERSE: Aabb = Aabb {
+    x: interval::UNIVERSE,
+    y: interval::UNIVERSE,
+    z: interval::UNIVERSE,
+};
+
+impl Aabb {
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        let mut aabb = Aabb { x, y, z };
+        aabb.pad_to_minimums();
+
+        aabb
+    }
+
+    pub fn from_points(a: Vec3, b: Vec3) -> Self {
+        Aabb::new(
+            Interval::new(f64::min(a.x, b.x), f64::max(a.x, b.x)),
+            Interval::new(f64::min(a.y, b.y), f64::max(a.y, b.y)),
+            Interval::new(f64::min(a.z, b.z), f64::max(a.z, b.z)),
+        )
+    }
+
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Self {
+        Aabb {
+            x: Interval::enclosing(a.x, b.x),
+            y: Interval::enclosing(a.y, b.y),
+            z: Interval::enclosing(a.z, b.z),
+        }
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let mut corners = [Vec3::default(); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Vec3::new(
+                if i & 1 == 0 { self.x.min } else { self.x.max },
+                if i & 2 == 0 { self.y.min } else { self.y.max },
+                if i & 4 == 0 { self.z.min } else { self.z.max },
+            );
+        }
+
+        corners
+    }
+
+    pub fn axis_interval(&self, n: usize) -> Interval {
+        match n {
+            1 => self.y,
+            2 => self.z,
+            _ => self.x,
+        }
+    }
+
+    pub fn longest_axis(&self) -> usize {
+        if self.x.size() > self.y.size() {
+            if self.x.size() > self.z.size() {
+                0
+            } else {
+                2
+            }
+        } else if self.y.size() > self.z.size() {
+            1
+        } else {
+            2
+        }
+    }
+
+    // The part of `ray_t` during which the ray is inside the box, if any.
+    pub fn clip(&self, r: &Ray, ray_t: Interval) -> Option<Interval> {
+        let mut ray_t = ray_t;
+        let origin = [r.origin.x, r.origin.y, r.origin.z];
+        let dir = [r.dir.x, r.dir.y, r.dir.z];
+
+        for axis in 0..3 {
+            let ax = self.axis_interval(axis);
+            let adinv = 1.0 / dir[axis];
+
+            let t0 = (ax.min - origin[axis]) * adinv;
+            let t1 = (ax.max - origin[axis]) * adinv;
+
+            if t0 < t1 {
+                ray_t.min = f64::max(t0, ray_t.min);
+                ray_t.max = f64::min(t1, ray_t.max);
+            } else {
+                ray_t.min = f64::max(t1, ray_t.min);
+                ray_t.max = f64::min(t0, ray_t.max);
+            }
+
+            if ray_t.max <= ray_t.min {
+                return None;
+            }
+        }
+
+        Some(ray_t)
+    }
+
+    fn pad_to_minimums(&mut self) {
+        let delta = 0.0001;
+        if self.x.size() < delta {
+            self.x = self.x.expand(delta);
+        }
+        if self.y.size() < delta {
+            self.y = self.y.expand(delta);
+        }
+        if self.z.size() < delta {
+            self.z = self.z.expand(delta);
+        }
+    }
+}
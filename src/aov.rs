@@ -0,0 +1,232 @@
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+use crate::{
+    color::Color,
+    exr::{self, Channel},
+    image::{Image, Region},
+    utils,
+    vec3::Vec3,
+};
+
+// Arbitrary output variables: what the camera rays see at their first hit,
+// for compositing and denoising alongside the rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    Albedo,
+    Normal,
+    Position,
+    Depth,
+    ObjectId,
+    MaterialId,
+}
+
+impl Aov {
+    pub const ALL: [Aov; 6] = [
+        Aov::Albedo,
+        Aov::Normal,
+        Aov::Position,
+        Aov::Depth,
+        Aov::ObjectId,
+        Aov::MaterialId,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Position => "position",
+            Aov::Depth => "depth",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+        }
+    }
+}
+
+impl fmt::Display for Aov {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// The AOVs of one pixel. Albedo and normal average over all of the pixel's
+// camera rays, counting misses as the background color and a zero normal;
+// position and depth average over the rays that hit something, and depth is
+// infinite if none did. The IDs are those of what most rays hit, zero for
+// the background.
+#[derive(Debug, Clone, Copy)]
+pub struct AovPixel {
+    pub albedo: Color,
+    pub normal: Vec3,
+    pub position: Vec3,
+    pub depth: f64,
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+impl Default for AovPixel {
+    fn default() -> Self {
+        AovPixel {
+            albedo: Color::default(),
+            normal: Vec3::default(),
+            position: Vec3::default(),
+            depth: f64::INFINITY,
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+}
+
+// The AOVs of a render's crop window, which like a cropped image remembers
+// its `Region` of the frame.
+#[derive(Debug, Clone)]
+pub struct AovBuffers {
+    width: usize,
+    height: usize,
+    pixels: Vec<AovPixel>,
+    region: Option<Region>,
+}
+
+impl AovBuffers {
+    pub fn new(width: usize, height: usize, region: Option<Region>) -> Self {
+        AovBuffers {
+            width,
+            height,
+            pixels: vec![AovPixel::default(); width * height],
+            region,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> AovPixel {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: AovPixel) {
+        self.pixels[y * self.width + x] = pixel;
+    }
+
+    // The raw values of `aov`, for float formats: vectors as RGB, and depth
+    // and IDs in all three channels.
+    pub fn image(&self, aov: Aov) -> Image {
+        self.map(|p| match aov {
+            Aov::Albedo => p.albedo,
+            Aov::Normal => p.normal,
+            Aov::Position => p.position,
+            Aov::Depth => Color::new(p.depth, p.depth, p.depth),
+            Aov::ObjectId => Color::new(1.0, 1.0, 1.0) * p.object_id as f64,
+            Aov::MaterialId => Color::new(1.0, 1.0, 1.0) * p.material_id as f64,
+        })
+    }
+
+    // `aov` made viewable in an 8-bit image: normals mapped from [-1, 1] to
+    // [0, 1], positions scaled to the box around them, depth from white
+    // nearby to black far away (by its inverse, so that a distant horizon
+    // does not wash out the foreground), and a color of its own for each ID.
+    // Misses are black.
+    pub fn preview(&self, aov: Aov) -> Image {
+        let mut low = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+        let mut high = -low;
+        for p in self.pixels.iter().filter(|p| p.depth.is_finite()) {
+            let v = match aov {
+                Aov::Depth => Vec3::new(p.depth, p.depth, p.depth),
+                _ => p.position,
+            };
+            low = Vec3::new(low.x.min(v.x), low.y.min(v.y), low.z.min(v.z));
+            high = Vec3::new(high.x.max(v.x), high.y.max(v.y), high.z.max(v.z));
+        }
+        let scale = |v: f64, low: f64, high: f64| {
+            if high > low {
+                (v - low) / (high - low)
+            } else {
+                0.5
+            }
+        };
+
+        self.map(|p| {
+            let color = match aov {
+                Aov::Albedo => return p.albedo,
+                Aov::Normal => 0.5 * (p.normal + Color::new(1.0, 1.0, 1.0)),
+                _ if !p.depth.is_finite() => Color::default(),
+                Aov::Position => Color::new(
+                    scale(p.position.x, low.x, high.x),
+                    scale(p.position.y, low.y, high.y),
+                    scale(p.position.z, low.z, high.z),
+                ),
+                Aov::Depth => {
+                    let t = if low.x > 0.0 {
+                        scale(1.0 / p.depth, 1.0 / high.x, 1.0 / low.x)
+                    } else {
+                        1.0 - scale(p.depth, low.x, high.x)
+                    };
+                    Color::new(t, t, t)
+                }
+                Aov::ObjectId => id_color(p.object_id),
+                Aov::MaterialId => id_color(p.material_id),
+            };
+            // Squared to undo the gamma applied when the image is saved.
+            color.elementwise_mul(color)
+        })
+    }
+
+    // A multi-layer EXR with `beauty` as the default RGB layer and a layer
+    // named after each AOV: `albedo.RGB`, `normal.XYZ`, `position.XYZ`,
+    // `depth.Z`, and `object_id.id` and `material_id.id` as unsigned ints.
+    pub fn write_exr(&self, output: &mut dyn Write, beauty: Option<&Image>) -> io::Result<()> {
+        let mut channels = Vec::new();
+        if let Some(beauty) = beauty {
+            channels.extend(beauty.exr_channels("", ["R", "G", "B"]));
+        }
+        for (aov, names) in [
+            (Aov::Albedo, ["R", "G", "B"]),
+            (Aov::Normal, ["X", "Y", "Z"]),
+            (Aov::Position, ["X", "Y", "Z"]),
+        ] {
+            channels.extend(self.image(aov).exr_channels(aov.name(), names));
+        }
+        channels.push(Channel::float(
+            "depth.Z",
+            self.pixels.iter().map(|p| p.depth as f32).collect(),
+        ));
+        channels.push(Channel::uint(
+            "object_id.id",
+            self.pixels.iter().map(|p| p.object_id).collect(),
+        ));
+        channels.push(Channel::uint(
+            "material_id.id",
+            self.pixels.iter().map(|p| p.material_id).collect(),
+        ));
+
+        exr::write(output, self.width, self.height, self.region, channels)
+    }
+
+    fn map(&self, f: impl Fn(&AovPixel) -> Color) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for j in 0..self.height {
+            for i in 0..self.width {
+                image.set_pixel(i, j, f(&self.pixels[j * self.width + i]));
+            }
+        }
+        image.set_region(self.region);
+        image
+    }
+}
+
+// A bright, stable color for an ID.
+fn id_color(id: u32) -> Color {
+    if id == 0 {
+        return Color::default();
+    }
+    let hash = utils::mix_seed(0, id as u64);
+    let channel = |shift: u32| 0.25 + 0.75 * ((hash >> shift) & 0xff) as f64 / 255.0;
+    Color::new(channel(0), channel(8), channel(16))
+}
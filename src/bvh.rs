@@ -0,0 +1,75 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::{
+    aabb::{self, Aabb},
+    hittable::{HitRecord, Hittable, HittableList},
+    interval::Interval,
+    ray::Ray,
+    stats,
+};
+
+// A bounding volume hierarchy over a list of objects. Each node splits its
+// objects in half along the longest axis of their combined bounding box.
+pub struct BvhNode {
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(list: HittableList) -> Self {
+        let mut objects = list.list;
+        BvhNode::from_objects(&mut objects)
+    }
+
+    fn from_objects(objects: &mut [Arc<dyn Hittable>]) -> Self {
+        let bbox = objects.iter().fold(aabb::EMPTY, |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        });
+
+        let (left, right): (Arc<dyn Hittable>, Arc<dyn Hittable>) = match objects.len() {
+            0 => {
+                let empty: Arc<dyn Hittable> = Arc::new(HittableList::new());
+                (empty.clone(), empty)
+            }
+            1 => (objects[0].clone(), objects[0].clone()),
+            2 => (objects[0].clone(), objects[1].clone()),
+            len => {
+                let axis = bbox.longest_axis();
+                objects.sort_by(|a, b| BvhNode::box_compare(a, b, axis));
+                let (lower, upper) = objects.split_at_mut(len / 2);
+                (
+                    Arc::new(BvhNode::from_objects(lower)),
+                    Arc::new(BvhNode::from_objects(upper)),
+                )
+            }
+        };
+
+        BvhNode { left, right, bbox }
+    }
+
+    fn box_compare(a: &Arc<dyn Hittable>, b: &Arc<dyn Hittable>, axis: usize) -> Ordering {
+        let a_min = a.bounding_box().axis_interval(axis).min;
+        let b_min = b.bounding_box().axis_interval(axis).min;
+        a_min.total_cmp(&b_min)
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_bvh_node_visit();
+        if self.bbox.clip(r, interval).is_none() {
+            return false;
+        }
+
+        let hit_left = self.left.hit(r, interval, rec);
+        let max = if hit_left { rec.t } else { interval.max };
+        let hit_right = self.right.hit(r, Interval::new(interval.min, max), rec);
+
+        hit_left || hit_right
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -1,79 +1,825 @@
-use std::{io::Write, ops::Neg};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    f64::consts::PI,
+    fmt,
+    ops::{Neg, Range},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use crate::{
-    color::{write_color, Color},
+    aov::{AovBuffers, AovPixel},
+    color::Color,
+    film::{Film, Filter, PixelStats},
     hittable::{HitRecord, Hittable},
+    image::{Image, Region},
     interval::Interval,
     ray::Ray,
-    utils::{self, degrees_to_radians, random_double},
+    sampler::{self, bounce_dimension, sample_unit_disk, Sampler, SamplerKind},
+    stats::{self, RenderStats},
+    utils::{self, degrees_to_radians, random_double, random_double_range},
     vec3::Vec3,
 };
 
-pub struct Camera {
+// Collects the camera parameters by name. Unset parameters keep the
+// defaults below; `build` checks the combination and sets up the viewport.
+#[derive(Debug, Clone)]
+pub struct CameraBuilder {
     aspect_ratio: f64,
     image_width: i32,
-    image_height: i32,
-    center: Vec3,
-    pixel00_loc: Vec3,
-    pixel_delta_u: Vec3,
-    pixel_delta_v: Vec3,
     samples_per_pixel: i32,
-    pixel_samples_scale: f64,
     max_depth: i32,
     vfov: f64,
     look_from: Vec3,
     look_at: Vec3,
     v_up: Vec3,
-    u: Vec3,
-    v: Vec3,
-    w: Vec3,
     defocus_angle: f64,
     focus_dist: f64,
-    defocus_disk_u: Vec3,
-    defocus_disk_v: Vec3,
+    background: Background,
+    projection: Projection,
+    physical: Option<PhysicalCamera>,
+    aperture: Aperture,
+    cat_eye: f64,
+    filter: Filter,
+    sampler: SamplerKind,
+    adaptive: Option<AdaptiveSampling>,
+    crop: Option<CropWindow>,
 }
 
-impl Camera {
-    pub fn new(
-        aspect_ratio: f64,
-        image_width: i32,
-        samples_per_pixel: i32,
-        max_depth: i32,
-        vfov: f64,
-        look_from: Vec3,
-        look_at: Vec3,
-        v_up: Vec3,
-        defocus_angle: f64,
-        focus_dist: f64,
-    ) -> Self {
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        CameraBuilder {
+            aspect_ratio: 1.0,
+            image_width: 100,
+            samples_per_pixel: 10,
+            max_depth: 10,
+            vfov: 90.0,
+            look_from: Vec3::new(0.0, 0.0, 0.0),
+            look_at: Vec3::new(0.0, 0.0, -1.0),
+            v_up: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 10.0,
+            background: Background::Sky,
+            projection: Projection::Perspective,
+            physical: None,
+            aperture: Aperture::Disk,
+            cat_eye: 0.0,
+            filter: Filter::default(),
+            sampler: SamplerKind::default(),
+            adaptive: None,
+            crop: None,
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn new() -> Self {
+        CameraBuilder::default()
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: i32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    pub fn samples_per_pixel(mut self, samples_per_pixel: i32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: i32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn look_from(mut self, look_from: Vec3) -> Self {
+        self.look_from = look_from;
+        self
+    }
+
+    pub fn look_at(mut self, look_at: Vec3) -> Self {
+        self.look_at = look_at;
+        self
+    }
+
+    pub fn v_up(mut self, v_up: Vec3) -> Self {
+        self.v_up = v_up;
+        self
+    }
+
+    pub fn defocus_angle(mut self, defocus_angle: f64) -> Self {
+        self.defocus_angle = defocus_angle;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = focus_dist;
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    // Derive the field of view, depth of field and exposure from a physical
+    // camera instead of `vfov` and `defocus_angle`.
+    pub fn physical(mut self, physical: PhysicalCamera) -> Self {
+        self.physical = Some(physical);
+        self
+    }
+
+    pub fn aperture(mut self, aperture: Aperture) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    // Clips the aperture of off-axis pixels against a second circle shifted
+    // towards the image edge, giving cat's-eye bokeh and natural vignetting.
+    // 0 disables the effect; at 1 the circles in the image corners are
+    // offset by a full aperture radius.
+    pub fn cat_eye(mut self, strength: f64) -> Self {
+        self.cat_eye = strength;
+        self
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn sampler(mut self, sampler: SamplerKind) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn adaptive(mut self, adaptive: AdaptiveSampling) -> Self {
+        self.adaptive = Some(adaptive);
+        self
+    }
+
+    pub fn crop(mut self, crop: CropWindow) -> Self {
+        self.crop = Some(crop);
+        self
+    }
+
+    pub fn build(&self) -> Result<Camera, CameraError> {
+        if self.image_width <= 0 {
+            return Err(CameraError::InvalidImageWidth(self.image_width));
+        }
+        if self.samples_per_pixel <= 0 {
+            return Err(CameraError::InvalidSamplesPerPixel(self.samples_per_pixel));
+        }
+        if self.max_depth <= 0 {
+            return Err(CameraError::InvalidMaxDepth(self.max_depth));
+        }
+        if !(self.aspect_ratio > 0.0 && self.aspect_ratio.is_finite()) {
+            return Err(CameraError::InvalidAspectRatio(self.aspect_ratio));
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(CameraError::InvalidVfov(self.vfov));
+        }
+        if !(self.defocus_angle >= 0.0 && self.defocus_angle < 180.0) {
+            return Err(CameraError::InvalidDefocusAngle(self.defocus_angle));
+        }
+        if !(self.focus_dist > 0.0 && self.focus_dist.is_finite()) {
+            return Err(CameraError::InvalidFocusDist(self.focus_dist));
+        }
+
+        match self.projection {
+            Projection::Orthographic { view_height } => {
+                if !(view_height > 0.0 && view_height.is_finite()) {
+                    return Err(CameraError::InvalidViewHeight(view_height));
+                }
+            }
+            Projection::Fisheye { fov, .. } => {
+                if !(fov > 0.0 && fov <= 360.0) {
+                    return Err(CameraError::InvalidFisheyeFov(fov));
+                }
+            }
+            Projection::StereoEquirectangular { eye_separation } => {
+                if !(eye_separation >= 0.0 && eye_separation.is_finite()) {
+                    return Err(CameraError::InvalidEyeSeparation(eye_separation));
+                }
+            }
+            Projection::Perspective | Projection::Equirectangular => {}
+        }
+
+        if let Some(physical) = &self.physical {
+            physical.validate()?;
+        }
+        match &self.aperture {
+            Aperture::Disk => {}
+            Aperture::Polygon { blades, .. } => {
+                if *blades < 3 {
+                    return Err(CameraError::InvalidApertureBlades(*blades));
+                }
+            }
+            Aperture::Mask(mask) => {
+                let (w, h) = (mask.width(), mask.height());
+                let lit = (0..h).any(|y| (0..w).any(|x| mask.pixel(x, y).luminance() > 0.0));
+                if !lit {
+                    return Err(CameraError::EmptyApertureMask);
+                }
+            }
+        }
+        if !(self.cat_eye >= 0.0 && self.cat_eye.is_finite()) {
+            return Err(CameraError::InvalidCatEye(self.cat_eye));
+        }
+        let filter_radius = self.filter.radius();
+        if !(filter_radius > 0.0 && filter_radius.is_finite()) {
+            return Err(CameraError::InvalidFilterRadius(filter_radius));
+        }
+        if let Some(adaptive) = &self.adaptive {
+            if !(1..=self.samples_per_pixel).contains(&adaptive.min_samples) {
+                return Err(CameraError::InvalidMinSamples {
+                    min: adaptive.min_samples,
+                    max: self.samples_per_pixel,
+                });
+            }
+            if !(adaptive.noise_threshold > 0.0 && adaptive.noise_threshold.is_finite()) {
+                return Err(CameraError::InvalidNoiseThreshold(adaptive.noise_threshold));
+            }
+        }
+
+        let view = self.look_from - self.look_at;
+        if view.near_zero() {
+            return Err(CameraError::LookFromEqualsLookAt);
+        }
+        if self.v_up.near_zero()
+            || Vec3::unit_vector(self.v_up)
+                .cross(Vec3::unit_vector(view))
+                .near_zero()
+        {
+            return Err(CameraError::VupParallelToView);
+        }
+
         let mut camera = Camera {
-            aspect_ratio,
-            image_width,
+            aspect_ratio: self.aspect_ratio,
+            image_width: self.image_width,
             image_height: 0,
             center: Vec3::default(),
             pixel00_loc: Vec3::default(),
             pixel_delta_u: Vec3::default(),
             pixel_delta_v: Vec3::default(),
-            samples_per_pixel,
-            pixel_samples_scale: 0.0,
-            max_depth,
-            vfov,
-            look_from,
-            look_at,
-            v_up,
+            samples_per_pixel: self.samples_per_pixel,
+            max_depth: self.max_depth,
+            vfov: self.vfov,
+            look_from: self.look_from,
+            look_at: self.look_at,
+            v_up: self.v_up,
             u: Vec3::default(),
             v: Vec3::default(),
             w: Vec3::default(),
-            defocus_angle,
-            focus_dist,
+            defocus_angle: self.defocus_angle,
+            focus_dist: self.focus_dist,
             defocus_disk_u: Vec3::default(),
             defocus_disk_v: Vec3::default(),
+            background: self.background,
+            projection: self.projection,
+            physical: self.physical,
+            defocus_radius: 0.0,
+            exposure: 1.0,
+            aperture: self.aperture.clone(),
+            cat_eye: self.cat_eye,
+            filter: self.filter,
+            sampler: self.sampler,
+            adaptive: self.adaptive,
+            crop_x: 0..0,
+            crop_y: 0..0,
         };
         camera.initialize();
 
-        camera
+        let (width, height) = (camera.image_width as usize, camera.image_height as usize);
+        (camera.crop_x, camera.crop_y) = match &self.crop {
+            Some(crop) => crop
+                .pixel_bounds(width, height)
+                .ok_or(CameraError::InvalidCropWindow(*crop))?,
+            None => (0..width, 0..height),
+        };
+
+        Ok(camera)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CameraError {
+    InvalidImageWidth(i32),
+    InvalidSamplesPerPixel(i32),
+    InvalidMaxDepth(i32),
+    InvalidAspectRatio(f64),
+    InvalidVfov(f64),
+    InvalidDefocusAngle(f64),
+    InvalidFocusDist(f64),
+    InvalidViewHeight(f64),
+    InvalidFisheyeFov(f64),
+    InvalidEyeSeparation(f64),
+    InvalidPhysicalParameter { name: &'static str, value: f64 },
+    InvalidApertureBlades(u32),
+    EmptyApertureMask,
+    InvalidCatEye(f64),
+    InvalidFilterRadius(f64),
+    InvalidMinSamples { min: i32, max: i32 },
+    InvalidNoiseThreshold(f64),
+    InvalidCropWindow(CropWindow),
+    LookFromEqualsLookAt,
+    VupParallelToView,
+}
+
+impl fmt::Display for CameraError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CameraError::InvalidImageWidth(n) => {
+                write!(
+                    f,
+                    "image width must be a positive number of pixels, got {n}"
+                )
+            }
+            CameraError::InvalidSamplesPerPixel(n) => {
+                write!(f, "samples per pixel must be positive, got {n}")
+            }
+            CameraError::InvalidMaxDepth(n) => write!(f, "max depth must be positive, got {n}"),
+            CameraError::InvalidAspectRatio(r) => {
+                write!(f, "aspect ratio must be positive, got {r}")
+            }
+            CameraError::InvalidVfov(deg) => write!(
+                f,
+                "vertical field of view must be between 0 and 180 degrees, got {deg}"
+            ),
+            CameraError::InvalidDefocusAngle(deg) => write!(
+                f,
+                "defocus angle must be at least 0 and below 180 degrees, got {deg}"
+            ),
+            CameraError::InvalidFocusDist(d) => {
+                write!(f, "focus distance must be positive, got {d}")
+            }
+            CameraError::InvalidViewHeight(h) => {
+                write!(f, "orthographic view height must be positive, got {h}")
+            }
+            CameraError::InvalidFisheyeFov(deg) => write!(
+                f,
+                "fisheye field of view must be above 0 and at most 360 degrees, got {deg}"
+            ),
+            CameraError::InvalidEyeSeparation(d) => {
+                write!(f, "eye separation must not be negative, got {d}")
+            }
+            CameraError::InvalidPhysicalParameter { name, value } => {
+                write!(f, "{name} must be positive, got {value}")
+            }
+            CameraError::InvalidApertureBlades(n) => {
+                write!(f, "a polygonal aperture needs at least 3 blades, got {n}")
+            }
+            CameraError::EmptyApertureMask => write!(f, "the aperture mask is entirely black"),
+            CameraError::InvalidCatEye(strength) => {
+                write!(f, "cat's-eye strength must not be negative, got {strength}")
+            }
+            CameraError::InvalidFilterRadius(r) => {
+                write!(f, "filter radius must be positive, got {r}")
+            }
+            CameraError::InvalidMinSamples { min, max } => write!(
+                f,
+                "minimum samples per pixel must be between 1 and the maximum of {max}, got {min}"
+            ),
+            CameraError::InvalidNoiseThreshold(t) => {
+                write!(f, "noise threshold must be positive, got {t}")
+            }
+            CameraError::InvalidCropWindow(crop) => {
+                write!(f, "crop window {crop} is empty or extends past the image")
+            }
+            CameraError::LookFromEqualsLookAt => {
+                write!(f, "look_from and look_at are the same point")
+            }
+            CameraError::VupParallelToView => {
+                write!(f, "v_up is parallel to the view direction")
+            }
+        }
+    }
+}
+
+impl Error for CameraError {}
+
+// How rays leave the camera. A perspective camera sends rays from a single
+// point (or the defocus disk) through the viewport; an orthographic camera
+// sends parallel rays from every point of a view plane `view_height` world
+// units tall.
+//
+// The remaining projections map pixels to directions around the camera
+// center and ignore `vfov` and the defocus settings. A fisheye fills the
+// largest circle that fits the image, covering `fov` degrees across its
+// diameter; pixels outside the circle stay black. An equirectangular image
+// spans 360 degrees of longitude across its width and 180 degrees of
+// latitude down its height, with `look_at` in the middle. The stereo variant
+// stacks a left-eye panorama over a right-eye one, with the eyes
+// `eye_separation` apart on a circle around the center.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective,
+    Orthographic { view_height: f64 },
+    Fisheye { mapping: FisheyeMapping, fov: f64 },
+    Equirectangular,
+    StereoEquirectangular { eye_separation: f64 },
+}
+
+// How the angle from the optical axis grows with the distance from the image
+// center: linearly for equidistant lenses, and so as to preserve solid angle
+// for equisolid ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FisheyeMapping {
+    Equidistant,
+    Equisolid,
+}
+
+// A camera described the way a photographer would. Lengths on the sensor
+// side are in millimetres while scene units are taken to be metres. The
+// defaults are a 50mm lens on a full-frame sensor exposed by the "sunny 16"
+// rule (f/16, 1/100 s at ISO 100), which is the exposure the rest of the
+// renderer assumes, so it leaves the image brightness unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalCamera {
+    pub focal_length: f64,
+    pub sensor_width: f64,
+    pub sensor_height: f64,
+    pub f_stop: f64,
+    pub shutter_time: f64,
+    pub iso: f64,
+}
+
+impl Default for PhysicalCamera {
+    fn default() -> Self {
+        PhysicalCamera {
+            focal_length: 50.0,
+            sensor_width: 36.0,
+            sensor_height: 24.0,
+            f_stop: 16.0,
+            shutter_time: 0.01,
+            iso: 100.0,
+        }
     }
+}
 
+impl PhysicalCamera {
+    const SUNNY_16: f64 = 0.01 * 100.0 / (16.0 * 16.0);
+
+    // The vertical field of view of an image with the given aspect ratio,
+    // fitted inside the sensor.
+    pub fn vfov(&self, aspect_ratio: f64) -> f64 {
+        let height = f64::min(self.sensor_height, self.sensor_width / aspect_ratio);
+        2.0 * f64::atan(height / (2.0 * self.focal_length)).to_degrees()
+    }
+
+    // Half the entrance pupil diameter, in metres.
+    pub fn aperture_radius(&self) -> f64 {
+        0.5 * self.focal_length / self.f_stop / 1000.0
+    }
+
+    // Sensor exposure relative to the sunny 16 rule: proportional to the
+    // shutter time and sensitivity and inversely to the aperture area.
+    pub fn exposure(&self) -> f64 {
+        self.shutter_time * self.iso / (self.f_stop * self.f_stop) / Self::SUNNY_16
+    }
+
+    fn validate(&self) -> Result<(), CameraError> {
+        let parameters = [
+            ("focal length", self.focal_length),
+            ("sensor width", self.sensor_width),
+            ("sensor height", self.sensor_height),
+            ("f-stop", self.f_stop),
+            ("shutter time", self.shutter_time),
+            ("ISO", self.iso),
+        ];
+        for (name, value) in parameters {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(CameraError::InvalidPhysicalParameter { name, value });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The shape of the lens opening, which out-of-focus highlights take on. A
+// polygon has its vertices on the unit circle, the first at `rotation`
+// degrees from the camera's horizontal. A mask is a grayscale image
+// stretched over the square around the unit circle; brighter pixels pass
+// more light.
+#[derive(Debug, Clone)]
+pub enum Aperture {
+    Disk,
+    Polygon { blades: u32, rotation: f64 },
+    Mask(Arc<Image>),
+}
+
+impl Aperture {
+    // A point on the aperture in lens-radius units for the 2D sample `u`.
+    fn sample(&self, u: (f64, f64)) -> Vec3 {
+        match self {
+            Aperture::Disk => sample_unit_disk(u),
+            Aperture::Polygon { blades, rotation } => {
+                // Pick one of the triangles fanning out from the center with
+                // the first coordinate, then reuse what is left of it for a
+                // uniform point inside the triangle.
+                let n = *blades as f64;
+                let k = f64::min(f64::floor(u.0 * n), n - 1.0);
+                let angle = |k: f64| degrees_to_radians(*rotation) + 2.0 * PI * k / n;
+                let (a, b) = (angle(k), angle(k + 1.0));
+
+                let (mut s, mut t) = (u.0 * n - k, u.1);
+                if s + t > 1.0 {
+                    (s, t) = (1.0 - s, 1.0 - t);
+                }
+                Vec3::new(s * a.cos() + t * b.cos(), s * a.sin() + t * b.sin(), 0.0)
+            }
+            Aperture::Mask(mask) => {
+                // Rejection sampling; `build` made sure some pixel is lit.
+                // It needs an unbounded number of draws, so it cannot use
+                // the sampler's dimensions.
+                loop {
+                    let p = Vec3::new(
+                        random_double_range(-1.0, 1.0),
+                        random_double_range(-1.0, 1.0),
+                        0.0,
+                    );
+                    let x = (0.5 * (p.x + 1.0) * mask.width() as f64) as usize;
+                    let y = (0.5 * (1.0 - p.y) * mask.height() as f64) as usize;
+                    if random_double() < mask.pixel(x, y).luminance() {
+                        return p;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The part of the image to render, from corner (x0, y0) up to but not
+// including (x1, y1), in pixels or in fractions of the image size. Pixels
+// near the window's edge also take samples from just outside it that the
+// reconstruction filter reaches, so the window's pixels come out exactly as
+// in a render of the whole image, and crops rendered separately can be
+// pieced back together.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CropWindow {
+    Pixels {
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    },
+    Normalized {
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    },
+}
+
+impl CropWindow {
+    // The columns and rows of a `width` x `height` image inside the window,
+    // or `None` if it is empty or does not fit. Normalized windows cover
+    // every pixel they overlap.
+    fn pixel_bounds(&self, width: usize, height: usize) -> Option<(Range<usize>, Range<usize>)> {
+        let (x, y) = match *self {
+            CropWindow::Pixels { x0, y0, x1, y1 } => (x0..x1, y0..y1),
+            CropWindow::Normalized { x0, y0, x1, y1 } => {
+                if ![x0, y0, x1, y1].iter().all(|t| (0.0..=1.0).contains(t)) {
+                    return None;
+                }
+                let (w, h) = (width as f64, height as f64);
+                (
+                    (x0 * w).floor() as usize..(x1 * w).ceil() as usize,
+                    (y0 * h).floor() as usize..(y1 * h).ceil() as usize,
+                )
+            }
+        };
+
+        let fits = !x.is_empty() && !y.is_empty() && x.end <= width && y.end <= height;
+        fits.then_some((x, y))
+    }
+}
+
+impl fmt::Display for CropWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CropWindow::Pixels { x0, y0, x1, y1 } => write!(f, "({x0}, {y0})-({x1}, {y1})"),
+            CropWindow::Normalized { x0, y0, x1, y1 } => {
+                write!(f, "({x0}, {y0})-({x1}, {y1})")
+            }
+        }
+    }
+}
+
+// Stops sampling a pixel once its estimate is good enough. Every pixel takes
+// at least `min_samples` and at most `samples_per_pixel` samples; in between
+// it stops when the standard error of its mean luminance falls below
+// `noise_threshold` times the mean. Dark pixels are judged against a mean
+// of at least 0.01 so that they do not have to resolve noise nobody can see.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: i32,
+    pub noise_threshold: f64,
+}
+
+impl AdaptiveSampling {
+    fn converged(&self, stats: &PixelStats) -> bool {
+        stats.samples >= self.min_samples as u32
+            && stats.standard_error() <= self.noise_threshold * f64::max(stats.mean(), 0.01)
+    }
+}
+
+impl Default for AdaptiveSampling {
+    fn default() -> Self {
+        AdaptiveSampling {
+            min_samples: 16,
+            noise_threshold: 0.01,
+        }
+    }
+}
+
+// AOVs are averaged over at most this many camera rays per pixel; more only
+// refine antialiasing that the beauty image's noise hides anyway.
+const MAX_AOV_SAMPLES: usize = 64;
+
+// Merges the films of finished rows into the frame in row order, holding
+// back rows that finish early. Floating-point sums depend on the order of
+// the additions, so this keeps the result independent of scheduling.
+struct RowMerger {
+    film: Film,
+    pending: BTreeMap<usize, Film>,
+    next: usize,
+    rows_done: usize,
+    stats: RenderStats,
+}
+
+impl RowMerger {
+    fn push(&mut self, j: usize, band: Film, stats: &RenderStats) {
+        self.rows_done += 1;
+        self.stats += stats;
+        self.pending.insert(j, band);
+        while let Some(band) = self.pending.remove(&self.next) {
+            self.film.merge(&band);
+            self.next += 1;
+        }
+    }
+}
+
+// What a ray sees when it escapes the scene.
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    Sky,
+    Solid(Color),
+}
+
+impl Background {
+    fn color(&self, r: &Ray) -> Color {
+        match *self {
+            Background::Sky => {
+                let unit_dir = Vec3::normalized(r.dir);
+                let t = 0.5 * (unit_dir.y + 1.0);
+                (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(color) => color,
+        }
+    }
+}
+
+// How far a render has come: `done` of `total` rows are finished, with
+// `paths` camera rays traced along paths of `rays` segments in total.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderProgress {
+    pub done: usize,
+    pub total: usize,
+    pub paths: u64,
+    pub rays: u64,
+    pub elapsed: Duration,
+}
+
+impl RenderProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.done as f64 / self.total as f64
+        }
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.rays as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+
+    // The mean number of segments per path.
+    pub fn average_depth(&self) -> f64 {
+        if self.paths == 0 {
+            0.0
+        } else {
+            self.rays as f64 / self.paths as f64
+        }
+    }
+
+    // The time left if the rest goes as fast as what is done, or `None`
+    // before anything is.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let remaining = (self.total - self.done) as f64 / self.done as f64;
+        Some(self.elapsed.mul_f64(remaining))
+    }
+}
+
+// Called from the render threads each time a row is finished, in order.
+pub type ProgressCallback = Arc<dyn Fn(&RenderProgress) + Send + Sync>;
+
+// With `stats`, the render adds what it did to the statistics there.
+#[derive(Clone)]
+pub struct RenderOptions {
+    pub threads: usize,
+    pub seed: u64,
+    pub progress: Option<ProgressCallback>,
+    pub stats: Option<Arc<Mutex<RenderStats>>>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+            progress: None,
+            stats: None,
+        }
+    }
+}
+
+impl fmt::Debug for RenderOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderOptions")
+            .field("threads", &self.threads)
+            .field("seed", &self.seed)
+            .field("progress", &self.progress.is_some())
+            .field("stats", &self.stats)
+            .finish()
+    }
+}
+
+pub struct Camera {
+    aspect_ratio: f64,
+    image_width: i32,
+    image_height: i32,
+    center: Vec3,
+    pixel00_loc: Vec3,
+    pixel_delta_u: Vec3,
+    pixel_delta_v: Vec3,
+    samples_per_pixel: i32,
+    max_depth: i32,
+    vfov: f64,
+    look_from: Vec3,
+    look_at: Vec3,
+    v_up: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    defocus_angle: f64,
+    focus_dist: f64,
+    defocus_disk_u: Vec3,
+    defocus_disk_v: Vec3,
+    background: Background,
+    projection: Projection,
+    physical: Option<PhysicalCamera>,
+    defocus_radius: f64,
+    exposure: f64,
+    aperture: Aperture,
+    cat_eye: f64,
+    filter: Filter,
+    sampler: SamplerKind,
+    adaptive: Option<AdaptiveSampling>,
+    crop_x: Range<usize>,
+    crop_y: Range<usize>,
+}
+
+impl Camera {
     fn initialize(&mut self) {
         self.image_height = (self.image_width as f64 / self.aspect_ratio) as i32;
         self.image_height = if self.image_height < 1 {
@@ -82,12 +828,22 @@ impl Camera {
             self.image_height
         };
 
-        self.pixel_samples_scale = 1.0 / self.samples_per_pixel as f64;
         self.center = self.look_from;
 
-        let theta = degrees_to_radians(self.vfov);
-        let h = f64::tan(theta / 2.0);
-        let viewport_height = 2.0 * h * self.focus_dist;
+        if let Some(physical) = self.physical {
+            self.vfov = physical.vfov(self.aspect_ratio);
+            self.exposure = physical.exposure();
+        }
+
+        // Only the planar projections use the viewport.
+        let viewport_height = match self.projection {
+            Projection::Orthographic { view_height } => view_height,
+            _ => {
+                let theta = degrees_to_radians(self.vfov);
+                let h = f64::tan(theta / 2.0);
+                2.0 * h * self.focus_dist
+            }
+        };
         let viewport_width = viewport_height * self.aspect_ratio;
 
         self.w = Vec3::unit_vector(self.look_from - self.look_at);
@@ -104,80 +860,483 @@ impl Camera {
             self.center - (self.focus_dist * self.w) - viewport_u / 2.0 - viewport_v / 2.0;
 
         self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
-        let defocus_radius =
-            self.focus_dist * f64::tan(utils::degrees_to_radians(self.defocus_angle / 2.0));
-        self.defocus_disk_u = self.u * defocus_radius;
-        self.defocus_disk_v = self.v * defocus_radius;
-    }
-
-    pub fn render(&self, world: &dyn Hittable, output: &mut dyn Write) -> std::io::Result<()> {
-        writeln!(
-            output,
-            "P3\n{} {}\n255",
-            self.image_width, self.image_height
-        )?;
-
-        for j in 0..self.image_height {
-            for i in 0..self.image_width {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j);
-                    pixel_color += ray_color(&r, self.max_depth, world)
-                }
-                write_color(output, &(pixel_color * self.pixel_samples_scale))?;
+        self.defocus_radius = match self.physical {
+            Some(physical) => physical.aperture_radius(),
+            None => self.focus_dist * f64::tan(utils::degrees_to_radians(self.defocus_angle / 2.0)),
+        };
+        self.defocus_disk_u = self.u * self.defocus_radius;
+        self.defocus_disk_v = self.v * self.defocus_radius;
+    }
+
+    pub fn render(&self, world: &dyn Hittable, options: &RenderOptions) -> Image {
+        self.develop(&self.render_film(world, options))
+    }
+
+    // Like `render`, but returns the film, which also holds the per-pixel
+    // sample counts.
+    pub fn render_film(&self, world: &dyn Hittable, options: &RenderOptions) -> Film {
+        let mut film = self.new_film();
+        self.render_pass(
+            world,
+            options,
+            &mut film,
+            0..self.samples_per_pixel as usize,
+        );
+        film
+    }
+
+    // An empty film the size of the image.
+    pub fn new_film(&self) -> Film {
+        Film::new(self.image_width as usize, self.image_height as usize)
+    }
+
+    pub fn image_size(&self) -> (usize, usize) {
+        (self.image_width as usize, self.image_height as usize)
+    }
+
+    pub fn samples_per_pixel(&self) -> usize {
+        self.samples_per_pixel as usize
+    }
+
+    // Adds the samples with indices in `samples` of every pixel in the crop
+    // window to `film`; pixels that adaptive sampling finds converged take
+    // none.
+    //
+    // Rows are handed out to worker threads one at a time, every sample
+    // reseeds the random number generator from the render seed, its pixel
+    // and its index, and the films of finished rows are merged strictly in
+    // row order. So the image does not depend on the number of threads or on
+    // scheduling, and a render split into passes takes the same samples as
+    // one rendered at once. The progress callback, if any, hears about every
+    // finished row.
+    pub fn render_pass(
+        &self,
+        world: &dyn Hittable,
+        options: &RenderOptions,
+        film: &mut Film,
+        samples: Range<usize>,
+    ) {
+        let (rows, _) = self.traced_bounds();
+        let start = Instant::now();
+        let next_row = AtomicUsize::new(rows.start);
+        let merger = Mutex::new(RowMerger {
+            film: self.new_film(),
+            pending: BTreeMap::new(),
+            next: rows.start,
+            rows_done: 0,
+            stats: RenderStats::default(),
+        });
+
+        thread::scope(|scope| {
+            for _ in 0..options.threads.max(1) {
+                scope.spawn(|| loop {
+                    let j = next_row.fetch_add(1, Ordering::Relaxed);
+                    if j >= rows.end {
+                        break;
+                    }
+                    let (band, stats) =
+                        self.render_row(world, j, options.seed, film, samples.clone());
+                    let mut merger = merger.lock().expect("render worker panicked");
+                    merger.push(j, band, &stats);
+                    if let Some(progress) = &options.progress {
+                        progress(&RenderProgress {
+                            done: merger.rows_done,
+                            total: rows.len(),
+                            paths: merger.stats.primary_rays,
+                            rays: merger.stats.rays(),
+                            elapsed: start.elapsed(),
+                        });
+                    }
+                });
             }
+        });
+
+        let merger = merger.into_inner().expect("render worker panicked");
+        film.merge(&merger.film);
+        if let Some(stats) = &options.stats {
+            *stats.lock().expect("render stats poisoned") += &merger.stats;
         }
-        Ok(())
     }
 
-    fn get_ray(&self, i: i32, j: i32) -> Ray {
-        let offset = Camera::sample_square();
-        let pixel_sample = self.pixel00_loc
-            + ((i as f64 + offset.x) * self.pixel_delta_u)
-            + ((j as f64 + offset.y) * self.pixel_delta_v);
+    // The image on a film this camera rendered, with the camera's exposure,
+    // cut down to the crop window.
+    pub fn develop(&self, film: &Film) -> Image {
+        self.crop_image(film.to_image(self.exposure))
+    }
 
-        let ray_origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else {
-            self.defocus_disk_sample()
+    // The crop window of a full-size image. A crop remembers where it came
+    // from, so that `Image::full_frame` can put it back.
+    fn crop_image(&self, image: Image) -> Image {
+        let (width, height) = (self.image_width as usize, self.image_height as usize);
+        if self.crop_x == (0..width) && self.crop_y == (0..height) {
+            return image;
+        }
+        image.crop(self.crop_x.clone(), self.crop_y.clone())
+    }
+
+    // The rows and columns to trace: the crop window, widened by how far
+    // the filter reaches.
+    fn traced_bounds(&self) -> (Range<usize>, Range<usize>) {
+        let reach = self.filter.reach();
+        let widen = |r: &Range<usize>, size: i32| {
+            r.start.saturating_sub(reach)..usize::min(r.end + reach, size as usize)
         };
+        (
+            widen(&self.crop_y, self.image_height),
+            widen(&self.crop_x, self.image_width),
+        )
+    }
 
-        let ray_direction = pixel_sample - ray_origin;
+    // An image of the samples taken per pixel, shading from blue for none to
+    // red for `samples_per_pixel`.
+    pub fn sample_heatmap(&self, film: &Film) -> Image {
+        let mut image = Image::new(film.width(), film.height());
+        for j in 0..film.height() {
+            for i in 0..film.width() {
+                let samples = film.pixel_stats(i, j).samples;
+                let t = samples as f64 / self.samples_per_pixel as f64;
+                let color = Color::new(t, 0.0, 1.0 - t);
+                // Squared to undo the gamma applied when the image is saved.
+                image.set_pixel(i, j, color.elementwise_mul(color));
+            }
+        }
 
-        Ray::new(ray_origin, ray_direction)
+        self.crop_image(image)
     }
 
-    fn sample_square() -> Vec3 {
-        Vec3::new(random_double() - 0.5, random_double() - 0.5, 0.0)
+    // The first-hit AOVs of the crop window, taken from the first camera
+    // rays of each pixel of the render: the same rays, for the same seed, as
+    // the image's first samples.
+    pub fn render_aovs(&self, world: &dyn Hittable, options: &RenderOptions) -> AovBuffers {
+        let (columns, rows) = (self.crop_x.clone(), self.crop_y.clone());
+        let region = (self.crop_x != (0..self.image_width as usize)
+            || self.crop_y != (0..self.image_height as usize))
+            .then_some(Region {
+                x: columns.start,
+                y: rows.start,
+                full_width: self.image_width as usize,
+                full_height: self.image_height as usize,
+            });
+        let next_row = AtomicUsize::new(rows.start);
+        let buffers = Mutex::new(AovBuffers::new(columns.len(), rows.len(), region));
+
+        thread::scope(|scope| {
+            for _ in 0..options.threads.max(1) {
+                scope.spawn(|| loop {
+                    let j = next_row.fetch_add(1, Ordering::Relaxed);
+                    if j >= rows.end {
+                        break;
+                    }
+                    let spp = self.samples_per_pixel as usize;
+                    let mut sampler = self.sampler.sampler(spp, options.seed);
+                    let row: Vec<AovPixel> = columns
+                        .clone()
+                        .map(|i| self.aov_pixel(world, i, j, options.seed, sampler.as_mut()))
+                        .collect();
+                    let mut buffers = buffers.lock().expect("render worker panicked");
+                    for (x, pixel) in row.into_iter().enumerate() {
+                        buffers.set_pixel(x, j - rows.start, pixel);
+                    }
+                });
+            }
+        });
+
+        buffers.into_inner().expect("render worker panicked")
     }
 
-    fn defocus_disk_sample(&self) -> Vec3 {
-        let p = Vec3::random_in_unit_disk();
-        self.center + self.defocus_disk_u * p.x + self.defocus_disk_v * p.y
+    fn aov_pixel(
+        &self,
+        world: &dyn Hittable,
+        i: usize,
+        j: usize,
+        seed: u64,
+        sampler: &mut dyn Sampler,
+    ) -> AovPixel {
+        let spp = self.samples_per_pixel as usize;
+        let pixel_seed = utils::mix_seed(seed, (j * self.image_width as usize + i) as u64);
+
+        let mut pixel = AovPixel::default();
+        let (mut rays, mut hits) = (0, 0);
+        let mut depth = 0.0;
+        // How many rays saw each (object, material) pair.
+        let mut ids: Vec<((u32, u32), usize)> = Vec::new();
+        for index in 0..usize::min(spp, MAX_AOV_SAMPLES) {
+            utils::seed(utils::mix_seed(pixel_seed, index as u64));
+            sampler.start_pixel_sample(i, j, index);
+            sampler.set_dimension(sampler::PIXEL_DIMENSION);
+            let (dx, dy) = sampler.get_2d();
+            let Some(r) = self.get_ray(i as f64 + dx, j as f64 + dy, sampler) else {
+                continue;
+            };
+            rays += 1;
+
+            let mut rec = HitRecord::default();
+            let id = if world.hit(&r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+                hits += 1;
+                pixel.albedo += rec.mat.albedo(&rec);
+                pixel.normal += rec.normal;
+                pixel.position += rec.p;
+                depth += self.depth(rec.p);
+                (rec.object_id, rec.mat.id())
+            } else {
+                pixel.albedo += self.background.color(&r);
+                (0, 0)
+            };
+            match ids.iter_mut().find(|(seen, _)| *seen == id) {
+                Some((_, count)) => *count += 1,
+                None => ids.push((id, 1)),
+            }
+        }
+
+        if rays > 0 {
+            pixel.albedo /= rays as f64;
+            pixel.normal /= rays as f64;
+        }
+        if hits > 0 {
+            pixel.position /= hits as f64;
+            pixel.depth = depth / hits as f64;
+        }
+        // The first of the most common pairs, so ties go to the earliest ray.
+        if let Some(&((object_id, material_id), _)) =
+            ids.iter().rev().max_by_key(|(_, count)| *count)
+        {
+            pixel.object_id = object_id;
+            pixel.material_id = material_id;
+        }
+        pixel
     }
-}
 
-fn ray_color(r: &Ray, depth: i32, world: &dyn Hittable) -> Color {
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
+    // The distance from the camera to `p`: along the view direction for
+    // the projections that have one, and straight-line for the panoramic
+    // ones.
+    fn depth(&self, p: Vec3) -> f64 {
+        match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                (p - self.center).dot(self.w.neg())
+            }
+            _ => (p - self.center).length(),
+        }
+    }
+
+    // The samples in `samples` of the pixels in row `j`, continuing from
+    // those already on `film`, and what it took to render them.
+    fn render_row(
+        &self,
+        world: &dyn Hittable,
+        j: usize,
+        seed: u64,
+        film: &Film,
+        samples: Range<usize>,
+    ) -> (Film, RenderStats) {
+        let width = self.image_width as usize;
+        let height = self.image_height as usize;
+        let mut band = Film::band_for_row(width, height, j, &self.filter);
+        let spp = self.samples_per_pixel as usize;
+        let mut sampler = self.sampler.sampler(spp, seed);
+        let mut row_stats = RenderStats::default();
+
+        let (_, columns) = self.traced_bounds();
+        for i in columns {
+            let mut stats = film.pixel_stats(i, j);
+            let pixel_seed = utils::mix_seed(seed, (j * width + i) as u64);
+            for index in samples.clone() {
+                if let Some(adaptive) = &self.adaptive {
+                    if adaptive.converged(&stats) {
+                        break;
+                    }
+                }
+
+                utils::seed(utils::mix_seed(pixel_seed, index as u64));
+                sampler.start_pixel_sample(i, j, index);
+                sampler.set_dimension(sampler::PIXEL_DIMENSION);
+                let (dx, dy) = sampler.get_2d();
+                let x = i as f64 + dx;
+                let y = j as f64 + dy;
+                // Blocked samples still count, as black, so that vignetting
+                // darkens the image.
+                let color = match self.get_ray(x, y, sampler.as_mut()) {
+                    Some(r) => self.ray_color(&r, 0, world, sampler.as_mut(), &mut row_stats),
+                    None => Color::default(),
+                };
+                band.add_sample(x, y, color, &self.filter);
+                band.record(i, j, color.luminance());
+                stats.add(color.luminance());
+            }
+        }
+
+        stats::take_counters(&mut row_stats);
+        (band, row_stats)
+    }
+
+    // A ray through film position (x, y), where pixel (i, j) covers
+    // [i, i + 1) x [j, j + 1), or `None` where the projection does not cover
+    // that position.
+    fn get_ray(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        sampler.set_dimension(sampler::TIME_DIMENSION);
+        let ray_time = sampler.get_1d();
+
+        match self.projection {
+            Projection::Perspective | Projection::Orthographic { .. } => {
+                let pixel_sample = self.pixel00_loc
+                    + ((x - 0.5) * self.pixel_delta_u)
+                    + ((y - 0.5) * self.pixel_delta_v);
+
+                // The orthographic viewport lies in the focus plane like the
+                // perspective one; its rays start at the matching point of
+                // the plane through the camera center.
+                if let Projection::Orthographic { .. } = self.projection {
+                    let ray_origin = pixel_sample + self.focus_dist * self.w;
+                    return Some(Ray::new(ray_origin, self.w.neg(), ray_time));
+                }
+
+                let ray_origin = if self.defocus_radius <= 0.0 {
+                    self.center
+                } else {
+                    self.defocus_disk_sample(x, y, sampler)?
+                };
+
+                let ray_direction = pixel_sample - ray_origin;
+
+                Some(Ray::new(ray_origin, ray_direction, ray_time))
+            }
+            Projection::Fisheye { mapping, fov } => {
+                let radius = 0.5 * f64::min(self.image_width as f64, self.image_height as f64);
+                let px = (x - 0.5 * self.image_width as f64) / radius;
+                let py = (0.5 * self.image_height as f64 - y) / radius;
+                let r = f64::sqrt(px * px + py * py);
+                if r > 1.0 {
+                    return None;
+                }
+
+                let half_fov = 0.5 * degrees_to_radians(fov);
+                let theta = match mapping {
+                    FisheyeMapping::Equidistant => r * half_fov,
+                    FisheyeMapping::Equisolid => 2.0 * f64::asin(r * f64::sin(0.5 * half_fov)),
+                };
+                let phi = f64::atan2(py, px);
+                let direction = self.to_world(Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    -theta.cos(),
+                ));
+
+                Some(Ray::new(self.center, direction, ray_time))
+            }
+            Projection::Equirectangular => {
+                let s = x / self.image_width as f64;
+                let t = y / self.image_height as f64;
+                let (direction, _) = self.equirectangular_direction(s, t);
+
+                Some(Ray::new(self.center, direction, ray_time))
+            }
+            Projection::StereoEquirectangular { eye_separation } => {
+                // Top half for the left eye, bottom half for the right.
+                let half_height = 0.5 * self.image_height as f64;
+                let (t, eye) = if y < half_height {
+                    (y / half_height, -1.0)
+                } else {
+                    ((y - half_height) / half_height, 1.0)
+                };
+                let s = x / self.image_width as f64;
+                let (direction, right) = self.equirectangular_direction(s, t);
+                let origin = self.center + (eye * 0.5 * eye_separation) * right;
+
+                Some(Ray::new(origin, direction, ray_time))
+            }
+        }
     }
 
-    let mut rec = HitRecord::default();
-    if world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+    // The view direction for a point (s, t) of an equirectangular image in
+    // [0, 1]^2, together with the horizontal direction to its right.
+    fn equirectangular_direction(&self, s: f64, t: f64) -> (Vec3, Vec3) {
+        let longitude = (s - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - t) * PI;
+        let direction = self.to_world(Vec3::new(
+            latitude.cos() * longitude.sin(),
+            latitude.sin(),
+            -latitude.cos() * longitude.cos(),
+        ));
+        let right = self.to_world(Vec3::new(longitude.cos(), 0.0, longitude.sin()));
+
+        (direction, right)
+    }
+
+    fn to_world(&self, local: Vec3) -> Vec3 {
+        local.x * self.u + local.y * self.v + local.z * self.w
+    }
+
+    // A point on the lens for a ray through image position (x, y), or `None`
+    // if cat's-eye vignetting blocks it.
+    fn defocus_disk_sample(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Vec3> {
+        sampler.set_dimension(sampler::LENS_DIMENSION);
+        let p = self.aperture.sample(sampler.get_2d());
+
+        if self.cat_eye > 0.0 {
+            let (half_w, half_h) = (
+                0.5 * self.image_width as f64,
+                0.5 * self.image_height as f64,
+            );
+            let half_diagonal = f64::sqrt(half_w * half_w + half_h * half_h);
+            let shift = Vec3::new(x - half_w, half_h - y, 0.0) * (self.cat_eye / half_diagonal);
+            if (p - shift).length_squared() > 1.0 {
+                return None;
+            }
+        }
+
+        Some(self.center + self.defocus_disk_u * p.x + self.defocus_disk_v * p.y)
+    }
+
+    // The radiance arriving along `r`, which is the `bounce`-th segment of
+    // its path. `stats` counts the rays and how the path ends.
+    fn ray_color(
+        &self,
+        r: &Ray,
+        bounce: usize,
+        world: &dyn Hittable,
+        sampler: &mut dyn Sampler,
+        stats: &mut RenderStats,
+    ) -> Color {
+        if bounce >= self.max_depth as usize {
+            stats.max_depth_reached += 1;
+            stats.end_path(bounce);
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        if bounce == 0 {
+            stats.primary_rays += 1;
+        } else {
+            stats.secondary_rays += 1;
+        }
+
+        let mut rec = HitRecord::default();
+        if !world.hit(r, Interval::new(0.001, f64::INFINITY), &mut rec) {
+            stats.escaped += 1;
+            stats.end_path(bounce + 1);
+            return self.background.color(r);
+        }
+
         let mut scattered = Ray::default();
         let mut attenuation = Color::default();
         let mut rec_copy = rec.clone();
-        if rec
+        let color_from_emission = rec.mat.emitted(rec.u, rec.v, rec.p);
+        sampler.set_dimension(bounce_dimension(bounce));
+        if !rec
             .mat
-            .scatter(r, &mut rec_copy, &mut attenuation, &mut scattered)
+            .scatter(r, &mut rec_copy, &mut attenuation, &mut scattered, sampler)
         {
-            return attenuation.elementwise_mul(ray_color(&scattered, depth - 1, world));
-        } else {
-            return Color::new(0.0, 0.0, 0.0);
+            stats.absorbed += 1;
+            stats.end_path(bounce + 1);
+            return color_from_emission;
         }
-    }
 
-    let unit_dir = Vec3::normalized(r.dir);
-    let t = 0.5 * (unit_dir.y + 1.0);
-    (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+        let color_from_scatter = attenuation.elementwise_mul(self.ray_color(
+            &scattered,
+            bounce + 1,
+            world,
+            sampler,
+            stats,
+        ));
+
+        color_from_emission + color_from_scatter
+    }
 }
@@ -0,0 +1,123 @@
+use std::{
+    fmt, fs,
+    io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use crate::{camera::CameraBuilder, film::Film};
+
+const MAGIC: &[u8; 8] = b"RTCKPT02";
+
+// The state of an unfinished render: the film so far and how many samples
+// of each pixel it holds. Every sample seeds the random number generator
+// from the render seed, its pixel and its index, so the seed and the sample
+// count are all the random state there is. The scene, the sample budget, the
+// pass size and a fingerprint of the camera and the scene file let a resumed
+// render check that it continues the same render and splits it into the same
+// passes, which makes the result identical to a render that was never
+// interrupted.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub scene: String,
+    pub seed: u64,
+    pub samples_per_pixel: usize,
+    pub pass_samples: usize,
+    pub fingerprint: u64,
+    pub samples_done: usize,
+    pub film: Film,
+}
+
+impl Checkpoint {
+    // A hash of the camera parameters, command-line overrides included, and
+    // of the scene file's contents; empty for a built-in scene. It is FNV-1a
+    // over the camera's debug output, which unlike `DefaultHasher` stays the
+    // same from one build to the next.
+    pub fn fingerprint(camera: &CameraBuilder, scene_source: &str) -> u64 {
+        let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+        fmt::write(&mut hasher, format_args!("{camera:?}\n{scene_source}"))
+            .expect("hashing cannot fail");
+        hasher.0
+    }
+
+    // Saves to a temporary file next to `path` and renames it into place, so
+    // a crash while saving leaves the previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+
+        let mut file = BufWriter::new(fs::File::create(&temp)?);
+        self.write(&mut file)?;
+        file.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        fs::rename(&temp, path)
+    }
+
+    // Loads a checkpoint of a `width` x `height` render.
+    pub fn load(path: impl AsRef<Path>, width: usize, height: usize) -> io::Result<Checkpoint> {
+        let mut file = BufReader::new(fs::File::open(path)?);
+        Checkpoint::read(&mut file, width, height)
+    }
+
+    pub fn write(&self, output: &mut dyn Write) -> io::Result<()> {
+        output.write_all(MAGIC)?;
+        output.write_all(&(self.scene.len() as u64).to_le_bytes())?;
+        output.write_all(self.scene.as_bytes())?;
+        for value in [
+            self.seed,
+            self.samples_per_pixel as u64,
+            self.pass_samples as u64,
+            self.fingerprint,
+            self.samples_done as u64,
+        ] {
+            output.write_all(&value.to_le_bytes())?;
+        }
+        self.film.write(output)
+    }
+
+    pub fn read(input: &mut dyn Read, width: usize, height: usize) -> io::Result<Checkpoint> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a raytracing checkpoint"));
+        }
+
+        let scene_len = read_u64(input)?;
+        if scene_len > 1 << 16 {
+            return Err(invalid("scene name too long"));
+        }
+        let mut scene = vec![0; scene_len as usize];
+        input.read_exact(&mut scene)?;
+        let scene = String::from_utf8(scene).map_err(|_| invalid("scene name is not UTF-8"))?;
+
+        Ok(Checkpoint {
+            scene,
+            seed: read_u64(input)?,
+            samples_per_pixel: read_u64(input)? as usize,
+            pass_samples: read_u64(input)? as usize,
+            fingerprint: read_u64(input)?,
+            samples_done: read_u64(input)? as usize,
+            film: Film::read(input, width, height)?,
+        })
+    }
+}
+
+fn read_u64(input: &mut dyn Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+struct Fnv1a(u64);
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use raytracing::{CropWindow, ImageFormat, SamplerKind};
+
+pub const USAGE: &str = "\
+Usage: raytracing [OPTIONS]
+       raytracing --merge <CROP>... [-o <PATH>] [-f <FORMAT>]
+       raytracing --worker <ADDRESS> [-j <N>]
+
+Options:
+      --scene <SCENE>     Built-in scene name or path to a JSON scene file
+                          [default: random_spheres]
+  -w, --width <PIXELS>    Image width; the height follows the aspect ratio
+  -s, --spp <N>           Samples per pixel
+  -d, --depth <N>         Maximum number of ray bounces
+      --sampler <SAMPLER> Sample generator: independent, stratified, halton
+                          or sobol [default: taken from the scene]
+      --noise-threshold <T>
+                          Sample adaptively, stopping a pixel once the
+                          relative error of its mean falls below T; -s sets
+                          the most samples a pixel may take
+      --min-spp <N>       Fewest samples of an adaptive pixel [default: 16]
+      --heatmap <PATH>    Also write an image of the samples taken per pixel
+      --stats <PATH>      Also write render statistics as JSON: rays, intersection
+                          tests per primitive, BVH node visits and path depths
+      --aovs <PATH>       Also write the albedo, normal, position, depth, object ID
+                          and material ID at the first hit: as layers of one EXR
+                          file with the image if PATH ends in .exr, otherwise as
+                          an image each, named like out.albedo.png for out.png
+      --pass-spp <N>      Render progressively in passes of N samples per
+                          pixel, rewriting the output after each pass
+      --snapshot-interval <SECONDS>
+                          Rewrite the output after the first pass to end at
+                          least this long after the last write; implies
+                          --pass-spp 1 unless given
+      --checkpoint <PATH> Save the render state to PATH between passes, so
+                          that an interrupted render can be resumed
+      --checkpoint-interval <SECONDS>
+                          Time between checkpoints [default: 300]
+      --resume            Continue from the --checkpoint file; the other
+                          options must match the interrupted render
+      --crop <X0,Y0,X1,Y1>
+                          Render only the pixels from (X0, Y0) up to but not
+                          including (X1, Y1)
+      --crop-window <X0,Y0,X1,Y1>
+                          Like --crop, in fractions of the image size
+      --full-frame        Write a cropped render at full size, black outside
+                          the crop, instead of just the crop
+      --merge <CROP>      Piece crops rendered to PPM or PFM files back into
+                          one image instead of rendering; repeat for each crop
+      --serve <ADDRESS>   Coordinate a distributed render: listen on ADDRESS
+                          (such as 127.0.0.1:7878) and hand out tiles of the
+                          image to the workers that connect
+      --tile-size <PIXELS>
+                          Width and height of distributed tiles [default: 32]
+      --worker <ADDRESS>  Render tiles for the coordinator at ADDRESS until
+                          it has none left; the coordinator sends the scene
+                          file and render options
+      --seed <N>          Random seed [default: 0]
+  -j, --threads <N>       Number of render threads [default: all cores]
+  -o, --output <PATH>     Output image path [default: output.ppm]
+  -f, --format <FORMAT>   Output format: ppm, png, pfm or exr
+                          [default: taken from the output extension]
+  -h, --help              Print this help and exit
+";
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub scene: String,
+    pub width: Option<i32>,
+    pub samples_per_pixel: Option<i32>,
+    pub max_depth: Option<i32>,
+    pub sampler: Option<SamplerKind>,
+    pub noise_threshold: Option<f64>,
+    pub min_samples: Option<i32>,
+    pub heatmap: Option<PathBuf>,
+    pub stats: Option<PathBuf>,
+    pub aovs: Option<PathBuf>,
+    pub pass_samples: Option<i32>,
+    pub snapshot_interval: Option<f64>,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: f64,
+    pub resume: bool,
+    pub crop: Option<CropWindow>,
+    pub full_frame: bool,
+    pub serve: Option<String>,
+    pub tile_size: usize,
+    pub seed: u64,
+    pub threads: Option<usize>,
+    pub output: PathBuf,
+    pub format: ImageFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergeOptions {
+    pub crops: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub format: ImageFormat,
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerOptions {
+    pub address: String,
+    pub threads: Option<usize>,
+}
+
+pub enum Command {
+    Render(Box<Options>),
+    Merge(MergeOptions),
+    Worker(WorkerOptions),
+    Help,
+}
+
+#[derive(Debug)]
+pub struct UsageError(String);
+
+impl fmt::Display for UsageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, UsageError> {
+    let mut scene = String::from("random_spheres");
+    let mut width = None;
+    let mut samples_per_pixel = None;
+    let mut max_depth = None;
+    let mut sampler = None;
+    let mut noise_threshold = None;
+    let mut min_samples = None;
+    let mut heatmap = None;
+    let mut stats = None;
+    let mut aovs = None;
+    let mut pass_samples = None;
+    let mut snapshot_interval = None;
+    let mut checkpoint = None;
+    let mut checkpoint_interval = 300.0;
+    let mut resume = false;
+    let mut crop = None;
+    let mut full_frame = false;
+    let mut merge = Vec::new();
+    let mut serve = None;
+    let mut tile_size = 32;
+    let mut worker = None;
+    let mut seed = 0;
+    let mut threads = None;
+    let mut output = PathBuf::from("output.ppm");
+    let mut format = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        // Accept both `--flag value` and `--flag=value`.
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.into())),
+            _ => (arg, None),
+        };
+        let mut value = || -> Result<String, UsageError> {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| UsageError(format!("missing value for `{flag}`")))
+        };
+
+        match flag.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "--scene" => scene = value()?,
+            "-w" | "--width" => width = Some(positive(&flag, &value()?)?),
+            "-s" | "--spp" => samples_per_pixel = Some(positive(&flag, &value()?)?),
+            "-d" | "--depth" => max_depth = Some(positive(&flag, &value()?)?),
+            "--sampler" => sampler = Some(value()?.parse().map_err(UsageError)?),
+            "--noise-threshold" => noise_threshold = Some(parse_value(&flag, &value()?)?),
+            "--min-spp" => min_samples = Some(positive(&flag, &value()?)?),
+            "--heatmap" => heatmap = Some(PathBuf::from(value()?)),
+            "--stats" => stats = Some(PathBuf::from(value()?)),
+            "--aovs" => aovs = Some(PathBuf::from(value()?)),
+            "--pass-spp" => pass_samples = Some(positive(&flag, &value()?)?),
+            "--snapshot-interval" => {
+                snapshot_interval = Some(non_negative(&flag, &value()?)?);
+            }
+            "--checkpoint" => checkpoint = Some(PathBuf::from(value()?)),
+            "--checkpoint-interval" => checkpoint_interval = non_negative(&flag, &value()?)?,
+            "--resume" => resume = true,
+            "--crop" => {
+                let [x0, y0, x1, y1] = corners(&flag, &value()?)?;
+                crop = Some(CropWindow::Pixels { x0, y0, x1, y1 });
+            }
+            "--crop-window" => {
+                let [x0, y0, x1, y1] = corners(&flag, &value()?)?;
+                crop = Some(CropWindow::Normalized { x0, y0, x1, y1 });
+            }
+            "--full-frame" => full_frame = true,
+            "--merge" => merge.push(PathBuf::from(value()?)),
+            "--serve" => serve = Some(value()?),
+            "--tile-size" => tile_size = positive(&flag, &value()?)? as usize,
+            "--worker" => worker = Some(value()?),
+            "--seed" => seed = parse_value(&flag, &value()?)?,
+            "-j" | "--threads" => threads = Some(positive(&flag, &value()?)? as usize),
+            "-o" | "--output" => output = PathBuf::from(value()?),
+            "-f" | "--format" => format = Some(value()?.parse().map_err(UsageError)?),
+            _ => return Err(UsageError(format!("unexpected argument `{flag}`"))),
+        }
+    }
+
+    if let Some(address) = worker {
+        return Ok(Command::Worker(WorkerOptions { address, threads }));
+    }
+    if serve.is_some()
+        && (crop.is_some()
+            || pass_samples.is_some()
+            || snapshot_interval.is_some()
+            || checkpoint.is_some()
+            || heatmap.is_some()
+            || stats.is_some()
+            || aovs.is_some())
+    {
+        return Err(UsageError(String::from(
+            "`--serve` renders the whole image in one go; it cannot be combined with \
+             `--crop`, progressive rendering, checkpoints, `--heatmap`, `--stats` or `--aovs`",
+        )));
+    }
+    if resume && checkpoint.is_none() {
+        return Err(UsageError(String::from(
+            "`--resume` needs the `--checkpoint` to resume from",
+        )));
+    }
+
+    let format = match format.or_else(|| ImageFormat::from_path(&output)) {
+        Some(format) => format,
+        None => {
+            return Err(UsageError(format!(
+                "cannot infer the image format of `{}`; pass --format",
+                output.display()
+            )))
+        }
+    };
+
+    if !merge.is_empty() {
+        return Ok(Command::Merge(MergeOptions {
+            crops: merge,
+            output,
+            format,
+        }));
+    }
+
+    Ok(Command::Render(Box::new(Options {
+        scene,
+        width,
+        samples_per_pixel,
+        max_depth,
+        sampler,
+        noise_threshold,
+        min_samples,
+        heatmap,
+        stats,
+        aovs,
+        pass_samples,
+        snapshot_interval,
+        checkpoint,
+        checkpoint_interval,
+        resume,
+        crop,
+        full_frame,
+        serve,
+        tile_size,
+        seed,
+        threads,
+        output,
+        format,
+    })))
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: &str) -> Result<T, UsageError> {
+    value
+        .parse()
+        .map_err(|_| UsageError(format!("invalid value `{value}` for `{flag}`")))
+}
+
+fn corners<T: FromStr>(flag: &str, value: &str) -> Result<[T; 4], UsageError> {
+    let invalid = || UsageError(format!("`{flag}` expects X0,Y0,X1,Y1, got `{value}`"));
+    let corners: Vec<T> = value
+        .split(',')
+        .map(|c| c.trim().parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    corners.try_into().map_err(|_| invalid())
+}
+
+fn non_negative(flag: &str, value: &str) -> Result<f64, UsageError> {
+    match parse_value::<f64>(flag, value)? {
+        x if x >= 0.0 && x.is_finite() => Ok(x),
+        _ => Err(UsageError(format!(
+            "`{flag}` must be a non-negative number, got `{value}`"
+        ))),
+    }
+}
+
+fn positive(flag: &str, value: &str) -> Result<i32, UsageError> {
+    match parse_value::<i32>(flag, value)? {
+        n if n > 0 => Ok(n),
+        _ => Err(UsageError(format!(
+            "`{flag}` must be a positive integer, got `{value}`"
+        ))),
+    }
+}
@@ -5,6 +5,11 @@ use crate::{interval::Interval, vec3::Vec3};
 pub type Color = Vec3;
 
 pub fn write_color(output: &mut dyn Write, color: &Color) -> std::io::Result<()> {
+    let [rbyte, gbyte, bbyte] = color_to_bytes(color);
+    writeln!(output, "{} {} {}", rbyte, gbyte, bbyte)
+}
+
+pub fn color_to_bytes(color: &Color) -> [u8; 3] {
     let r = Color::linear_to_gamma(color.x);
     let g = Color::linear_to_gamma(color.y);
     let b = Color::linear_to_gamma(color.z);
@@ -14,7 +19,7 @@ pub fn write_color(output: &mut dyn Write, color: &Color) -> std::io::Result<()>
     let gbyte = 256.0 * intensity.clamp(g);
     let bbyte = 256.0 * intensity.clamp(b);
 
-    writeln!(output, "{} {} {}", rbyte as u8, gbyte as u8, bbyte as u8)
+    [rbyte as u8, gbyte as u8, bbyte as u8]
 }
 
 impl Color {
@@ -26,4 +31,8 @@ impl Color {
             0.0
         }
     }
+
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.x + 0.7152 * self.y + 0.0722 * self.z
+    }
 }
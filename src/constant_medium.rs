@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    color::Color,
+    hittable::{HitRecord, Hittable},
+    interval::{self, Interval},
+    material::{Isotropic, Material},
+    ray::Ray,
+    texture::Texture,
+    utils::random_double,
+    vec3::Vec3,
+};
+
+// A volume of constant density filling a convex boundary, such as smoke or
+// fog. Rays scatter at an exponentially distributed distance inside it.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable>,
+    neg_inv_density: f64,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    pub fn new(boundary: Arc<dyn Hittable>, density: f64, albedo: Color) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::new(albedo)),
+        }
+    }
+
+    pub fn from_texture(boundary: Arc<dyn Hittable>, density: f64, tex: Arc<dyn Texture>) -> Self {
+        ConstantMedium {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Arc::new(Isotropic::from_texture(tex)),
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        let mut rec1 = HitRecord::default();
+        let mut rec2 = HitRecord::default();
+
+        if !self.boundary.hit(r, interval::UNIVERSE, &mut rec1) {
+            return false;
+        }
+        if !self
+            .boundary
+            .hit(r, Interval::new(rec1.t + 0.0001, f64::INFINITY), &mut rec2)
+        {
+            return false;
+        }
+
+        let t_enter = f64::max(rec1.t, interval.min);
+        let t_exit = f64::min(rec2.t, interval.max);
+        if t_enter >= t_exit {
+            return false;
+        }
+        let t_enter = f64::max(t_enter, 0.0);
+
+        let ray_length = r.dir.length();
+        let distance_inside_boundary = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * f64::ln(random_double());
+        if hit_distance > distance_inside_boundary {
+            return false;
+        }
+
+        rec.t = t_enter + hit_distance / ray_length;
+        rec.p = r.at(rec.t);
+        // Normal and face are arbitrary for a volume.
+        rec.normal = Vec3::new(1.0, 0.0, 0.0);
+        rec.front_face = true;
+        rec.mat = self.phase_function.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+}
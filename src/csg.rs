@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable, Span},
+    interval::Interval,
+    ray::Ray,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+pub struct Csg {
+    op: CsgOp,
+    left: Arc<dyn Hittable>,
+    right: Arc<dyn Hittable>,
+    bbox: Aabb,
+}
+
+struct Event {
+    rec: HitRecord,
+    from_left: bool,
+    entering: bool,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        let bbox = match op {
+            CsgOp::Union => Aabb::surrounding(&left.bounding_box(), &right.bounding_box()),
+            CsgOp::Intersection | CsgOp::Difference => left.bounding_box(),
+        };
+
+        Csg {
+            op,
+            left,
+            right,
+            bbox,
+        }
+    }
+
+    pub fn union(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Intersection, left, right)
+    }
+
+    pub fn difference(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Csg::new(CsgOp::Difference, left, right)
+    }
+
+    fn events(spans: Vec<Span>, from_left: bool, events: &mut Vec<Event>) {
+        for span in spans {
+            events.push(Event {
+                rec: span.enter,
+                from_left,
+                entering: true,
+            });
+            events.push(Event {
+                rec: span.exit,
+                from_left,
+                entering: false,
+            });
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        for span in self.spans(r) {
+            for boundary in [span.enter, span.exit] {
+                if interval.surrounds(boundary.t) {
+                    *rec = boundary;
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        let mut events = Vec::new();
+        Csg::events(self.left.spans(r), true, &mut events);
+        Csg::events(self.right.spans(r), false, &mut events);
+        events.sort_by(|a, b| a.rec.t.total_cmp(&b.rec.t));
+
+        let mut spans = Vec::new();
+        let mut enter: Option<HitRecord> = None;
+        let (mut in_left, mut in_right) = (false, false);
+
+        for event in events {
+            let was_inside = self.op.inside(in_left, in_right);
+            if event.from_left {
+                in_left = event.entering;
+            } else {
+                in_right = event.entering;
+            }
+            let is_inside = self.op.inside(in_left, in_right);
+            if was_inside == is_inside {
+                continue;
+            }
+
+            // A subtracted object's surface bounds the result from the other
+            // side, so its outward normal flips and with it the face the ray
+            // sees.
+            let mut rec = event.rec;
+            if self.op == CsgOp::Difference && !event.from_left {
+                rec.front_face = !rec.front_face;
+            }
+
+            if is_inside {
+                enter = Some(rec);
+            } else if let Some(enter) = enter.take() {
+                spans.push(Span { enter, exit: rec });
+            }
+        }
+
+        spans
+    }
+}
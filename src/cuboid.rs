@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+pub struct Cuboid {
+    min: Vec3,
+    max: Vec3,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Cuboid {
+    pub fn new(a: Vec3, b: Vec3, mat: Arc<dyn Material>) -> Self {
+        let min = Vec3::new(f64::min(a.x, b.x), f64::min(a.y, b.y), f64::min(a.z, b.z));
+        let max = Vec3::new(f64::max(a.x, b.x), f64::max(a.y, b.y), f64::max(a.z, b.z));
+
+        Cuboid {
+            min,
+            max,
+            mat,
+            bbox: Aabb::from_points(min, max),
+        }
+    }
+}
+
+fn component(v: Vec3, axis: usize) -> f64 {
+    match axis {
+        0 => v.x,
+        1 => v.y,
+        _ => v.z,
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Cuboid);
+        let mut t_near = f64::NEG_INFINITY;
+        let mut t_far = f64::INFINITY;
+        let mut near_axis = 0;
+        let mut far_axis = 0;
+
+        for axis in 0..3 {
+            let inv = 1.0 / component(r.dir, axis);
+            let origin = component(r.origin, axis);
+            let mut t0 = (component(self.min, axis) - origin) * inv;
+            let mut t1 = (component(self.max, axis) - origin) * inv;
+            if inv < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            if t0 > t_near {
+                t_near = t0;
+                near_axis = axis;
+            }
+            if t1 < t_far {
+                t_far = t1;
+                far_axis = axis;
+            }
+            if t_near > t_far {
+                return false;
+            }
+        }
+
+        let (t, axis, sign) = if interval.surrounds(t_near) {
+            (t_near, near_axis, -1.0)
+        } else if interval.surrounds(t_far) {
+            (t_far, far_axis, 1.0)
+        } else {
+            return false;
+        };
+
+        let direction = sign * component(r.dir, axis).signum();
+        let outward_normal = match axis {
+            0 => Vec3::new(direction, 0.0, 0.0),
+            1 => Vec3::new(0.0, direction, 0.0),
+            _ => Vec3::new(0.0, 0.0, direction),
+        };
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let local = rec.p - self.min;
+        let size = self.max - self.min;
+        let (u_axis, v_axis) = match axis {
+            0 => (2, 1),
+            1 => (0, 2),
+            _ => (0, 1),
+        };
+        rec.u = component(local, u_axis) / component(size, u_axis);
+        rec.v = component(local, v_axis) / component(size, v_axis);
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
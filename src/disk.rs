@@ -0,0 +1,81 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    onb::Onb,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+pub struct Disk {
+    center: Vec3,
+    basis: Onb,
+    radius: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Disk {
+    // Panics if `radius` is not positive, which would leave the UVs
+    // undefined.
+    pub fn new(center: Vec3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        assert!(radius > 0.0, "disk radius must be positive, got {radius}");
+        let basis = Onb::new(normal);
+        let n = basis.w;
+        let extent = |c: f64, nc: f64| {
+            let e = radius * f64::sqrt(f64::max(0.0, 1.0 - nc * nc));
+            Interval::new(c - e, c + e)
+        };
+        Disk {
+            center,
+            basis,
+            radius,
+            mat,
+            bbox: Aabb::new(
+                extent(center.x, n.x),
+                extent(center.y, n.y),
+                extent(center.z, n.z),
+            ),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Disk);
+        let denom = self.basis.w.dot(r.dir);
+        if f64::abs(denom) < 1e-8 {
+            return false;
+        }
+
+        let t = (self.center - r.origin).dot(self.basis.w) / denom;
+        if !interval.surrounds(t) {
+            return false;
+        }
+
+        let p = r.at(t);
+        let local = self.basis.to_local(p - self.center);
+        let dist_squared = local.x * local.x + local.y * local.y;
+        if dist_squared > self.radius * self.radius {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        let phi = f64::atan2(local.y, local.x) + PI;
+        rec.u = phi / (2.0 * PI);
+        rec.v = f64::sqrt(dist_squared) / self.radius;
+        rec.set_face_normal(r, &self.basis.w);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
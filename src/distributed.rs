@@ -0,0 +1,535 @@
+// Tile-based rendering across processes. A coordinator (`--serve`) splits
+// the image into tiles and hands them out to worker processes (`--worker`)
+// over TCP; each tile is rendered as a crop window, so the assembled image
+// is the same as one rendered by a single process.
+//
+// The protocol is line based. When a worker connects, the coordinator sends
+//
+//     JOB <n>
+//
+// followed by n lines, its own command-line arguments, and then
+//
+//     SCENE <n>
+//
+// followed by the n bytes of the scene file, or with n = 0 for a built-in
+// scene. From these the worker loads the scene and sets up the camera; files
+// the scene refers to, such as textures, are looked up relative to the
+// coordinator's scene path. Then, as long as there are tiles left, the
+// coordinator sends
+//
+//     TILE <x0> <y0> <x1> <y1>
+//
+// and the worker answers with
+//
+//     PIXELS <x0> <y0> <x1> <y1> <paths> <rays>
+//
+// giving the number of camera rays and ray segments it traced, followed by
+// the tile's linear RGB as little-endian f64 triples, row by row; or with
+// `ERROR <message>` if it cannot render the job. While it renders, the
+// worker sends `BUSY` every few seconds, so that the coordinator can tell a
+// slow tile from a worker that has gone away. `END` tells the worker there
+// is nothing more to do. A tile whose worker fails, disconnects or goes
+// quiet for `WORKER_TIMEOUT` goes back to the queue for another worker, up
+// to `MAX_TILE_FAILURES` times. `ERROR` means that the job itself is broken,
+// such as a scene the worker cannot load; after `MAX_JOB_ERRORS` of them
+// with no tile finished in between, the render stops with the error.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use raytracing::{Camera, Color, CropWindow, HittableList, Image, RenderOptions, RenderProgress};
+
+use crate::{
+    cli::{self, Command, WorkerOptions},
+    progress::ProgressBar,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const WORKER_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_TILE_FAILURES: usize = 5;
+const MAX_JOB_ERRORS: usize = 3;
+
+#[derive(Debug, Clone, Copy)]
+struct Tile {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl Tile {
+    fn width(&self) -> usize {
+        self.x1 - self.x0
+    }
+
+    fn height(&self) -> usize {
+        self.y1 - self.y0
+    }
+}
+
+struct Progress {
+    // The tiles still to render, with how many times each has failed.
+    pending: VecDeque<(Tile, usize)>,
+    image: Image,
+    done: usize,
+    paths: u64,
+    rays: u64,
+    // `ERROR` replies since the last finished tile.
+    job_errors: usize,
+    // Why the render was given up.
+    error: Option<String>,
+}
+
+impl Progress {
+    fn running(&self, total: usize) -> bool {
+        self.done < total && self.error.is_none()
+    }
+}
+
+// What the coordinator shares with the threads talking to the workers.
+struct Shared<'a> {
+    job: &'a [String],
+    scene: &'a str,
+    progress: Mutex<Progress>,
+    total: usize,
+    start: Instant,
+    bar: &'a ProgressBar,
+}
+
+// Serves the tiles of `camera`'s image on `address` until every tile is
+// back, or until the render has to be given up. `args` are the coordinator's command-line arguments, which the
+// workers render with, and `scene` the contents of its scene file, empty
+// for a built-in scene; `bar` counts the finished tiles.
+pub fn serve(
+    address: &str,
+    args: &[String],
+    scene: &str,
+    camera: &Camera,
+    tile_size: usize,
+    bar: &ProgressBar,
+) -> Result<Image, String> {
+    let (width, height) = camera.image_size();
+    let mut pending = VecDeque::new();
+    for y0 in (0..height).step_by(tile_size) {
+        for x0 in (0..width).step_by(tile_size) {
+            let tile = Tile {
+                x0,
+                y0,
+                x1: usize::min(x0 + tile_size, width),
+                y1: usize::min(y0 + tile_size, height),
+            };
+            pending.push_back((tile, 0));
+        }
+    }
+    let job = job_args(args);
+    let shared = Shared {
+        job: &job,
+        scene,
+        total: pending.len(),
+        progress: Mutex::new(Progress {
+            pending,
+            image: Image::new(width, height),
+            done: 0,
+            paths: 0,
+            rays: 0,
+            job_errors: 0,
+            error: None,
+        }),
+        start: Instant::now(),
+        bar,
+    };
+    let total = shared.total;
+
+    let listener = TcpListener::bind(address).map_err(|err| format!("{address}: {err}"))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|err| format!("{address}: {err}"))?;
+    println!("Serving {total} tiles on {address}");
+
+    thread::scope(|scope| {
+        while shared
+            .progress
+            .lock()
+            .expect("tile server panicked")
+            .running(total)
+        {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    let shared = &shared;
+                    scope.spawn(move || {
+                        if let Err(err) = serve_worker(stream, shared) {
+                            shared.bar.clear();
+                            eprintln!("worker {peer}: {err}");
+                        }
+                    });
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => return Err(format!("{address}: {err}")),
+            }
+        }
+        Ok(())
+    })?;
+
+    let progress = shared.progress.into_inner().expect("tile server panicked");
+    match progress.error {
+        Some(error) => Err(error),
+        None => Ok(progress.image),
+    }
+}
+
+// The arguments that describe the render, without the ones that only
+// concern the coordinator.
+fn job_args(args: &[String]) -> Vec<String> {
+    let mut job = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--serve" | "--tile-size" => {
+                args.next();
+            }
+            _ if arg.starts_with("--serve=") || arg.starts_with("--tile-size=") => {}
+            _ => job.push(arg.clone()),
+        }
+    }
+    job
+}
+
+fn serve_worker(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(WORKER_TIMEOUT))?;
+    stream.set_write_timeout(Some(WORKER_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    writeln!(writer, "JOB {}", shared.job.len())?;
+    for arg in shared.job {
+        writeln!(writer, "{arg}")?;
+    }
+    writeln!(writer, "SCENE {}", shared.scene.len())?;
+    writer.write_all(shared.scene.as_bytes())?;
+
+    loop {
+        let Some((tile, failures)) = next_tile(&shared.progress, shared.total) else {
+            return writeln!(writer, "END");
+        };
+
+        match render_remote(&mut reader, &mut writer, tile) {
+            Ok(Reply::Pixels(pixels, paths, rays)) => {
+                let mut progress = shared.progress.lock().expect("tile server panicked");
+                progress.job_errors = 0;
+                for j in 0..tile.height() {
+                    for i in 0..tile.width() {
+                        let color = pixels[j * tile.width() + i];
+                        progress.image.set_pixel(tile.x0 + i, tile.y0 + j, color);
+                    }
+                }
+                progress.done += 1;
+                progress.paths += paths;
+                progress.rays += rays;
+                shared.bar.update(&RenderProgress {
+                    done: progress.done,
+                    total: shared.total,
+                    paths: progress.paths,
+                    rays: progress.rays,
+                    elapsed: shared.start.elapsed(),
+                });
+            }
+            Ok(Reply::Error(message)) => {
+                let mut progress = shared.progress.lock().expect("tile server panicked");
+                progress.pending.push_back((tile, failures));
+                progress.job_errors += 1;
+                if progress.job_errors >= MAX_JOB_ERRORS && progress.error.is_none() {
+                    progress.error = Some(format!(
+                        "giving up after {} workers could not render the job: {message}",
+                        progress.job_errors
+                    ));
+                }
+                return Err(io::Error::other(message));
+            }
+            Err(err) => {
+                let err = match err.kind() {
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut => io::Error::new(
+                        ErrorKind::TimedOut,
+                        format!("no reply in {} seconds", WORKER_TIMEOUT.as_secs()),
+                    ),
+                    _ => err,
+                };
+                let mut progress = shared.progress.lock().expect("tile server panicked");
+                let failures = failures + 1;
+                if failures < MAX_TILE_FAILURES {
+                    progress.pending.push_back((tile, failures));
+                } else if progress.error.is_none() {
+                    progress.error = Some(format!(
+                        "giving up after the tile at ({}, {}) failed {failures} times, last with: {err}",
+                        tile.x0, tile.y0
+                    ));
+                }
+                return Err(err);
+            }
+        }
+    }
+}
+
+// The next tile to hand out, or `None` once every tile is done or the
+// render has been given up. While the last tiles are out with other workers
+// this waits, in case one of them fails and its tile comes back.
+fn next_tile(progress: &Mutex<Progress>, total: usize) -> Option<(Tile, usize)> {
+    loop {
+        {
+            let mut progress = progress.lock().expect("tile server panicked");
+            if !progress.running(total) {
+                return None;
+            }
+            if let Some(tile) = progress.pending.pop_front() {
+                return Some(tile);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// A worker's answer to a tile: its pixels with the numbers of camera rays
+// and ray segments traced, or why it cannot render the job.
+enum Reply {
+    Pixels(Vec<Color>, u64, u64),
+    Error(String),
+}
+
+fn render_remote(
+    reader: &mut BufReader<TcpStream>,
+    writer: &mut TcpStream,
+    tile: Tile,
+) -> io::Result<Reply> {
+    let request = format!("{} {} {} {}", tile.x0, tile.y0, tile.x1, tile.y1);
+    writeln!(writer, "TILE {request}")?;
+
+    let reply = loop {
+        let line = read_line(reader)?;
+        if line != "BUSY" {
+            break line;
+        }
+    };
+    if let Some(message) = reply.strip_prefix("ERROR ") {
+        return Ok(Reply::Error(message.to_string()));
+    }
+    let counts = reply
+        .strip_prefix(&format!("PIXELS {request} "))
+        .and_then(|counts| counts.split_once(' '))
+        .and_then(|(paths, rays)| Some((paths.parse().ok()?, rays.parse().ok()?)));
+    let Some((paths, rays)) = counts else {
+        return Err(protocol_error(&reply));
+    };
+
+    let mut pixels = Vec::with_capacity(tile.width() * tile.height());
+    for _ in 0..tile.width() * tile.height() {
+        let [r, g, b] = [read_f64(reader)?, read_f64(reader)?, read_f64(reader)?];
+        pixels.push(Color::new(r, g, b));
+    }
+    Ok(Reply::Pixels(pixels, paths, rays))
+}
+
+// Connects to the coordinator and renders the tiles it sends.
+pub fn work(options: &WorkerOptions) -> Result<(), String> {
+    let address = &options.address;
+    let stream = TcpStream::connect(address).map_err(|err| format!("{address}: {err}"))?;
+    println!("Connected to {address}");
+    let tiles = render_tiles(stream, options.threads).map_err(|err| format!("{address}: {err}"))?;
+    println!("Rendered {tiles} tiles");
+    Ok(())
+}
+
+fn render_tiles(stream: TcpStream, threads: Option<usize>) -> io::Result<usize> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let header = read_line(&mut reader)?;
+    let count: usize = header
+        .strip_prefix("JOB ")
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| protocol_error(&header))?;
+    let mut args = Vec::with_capacity(count);
+    for _ in 0..count {
+        args.push(read_line(&mut reader)?);
+    }
+    let source = read_scene(&mut reader)?;
+
+    let (scene, render_options) = match job(args, source.as_deref(), threads) {
+        Ok(job) => job,
+        Err(err) => {
+            writeln!(writer, "ERROR {err}")?;
+            return Err(io::Error::other(err));
+        }
+    };
+
+    let mut tiles = 0;
+    loop {
+        let line = read_line(&mut reader)?;
+        if line == "END" {
+            return Ok(tiles);
+        }
+        let tile = parse_tile(&line).ok_or_else(|| protocol_error(&line))?;
+
+        let crop = CropWindow::Pixels {
+            x0: tile.x0,
+            y0: tile.y0,
+            x1: tile.x1,
+            y1: tile.y1,
+        };
+        let camera = match scene.camera.clone().crop(crop).build() {
+            Ok(camera) => camera,
+            Err(err) => {
+                writeln!(writer, "ERROR camera: {err}")?;
+                return Err(io::Error::other(err));
+            }
+        };
+        // The last report of the render holds its totals.
+        let counts = Arc::new(Mutex::new(RenderProgress::default()));
+        let report = Arc::clone(&counts);
+        let render_options = RenderOptions {
+            progress: Some(Arc::new(move |progress| {
+                *report.lock().expect("render worker panicked") = *progress;
+            })),
+            ..render_options.clone()
+        };
+        let image = render_with_heartbeat(&camera, &scene.world, &render_options, &mut writer)?;
+        let counts = *counts.lock().expect("render worker panicked");
+
+        let mut reply = Vec::with_capacity(64 + tile.width() * tile.height() * 24);
+        writeln!(
+            reply,
+            "PIXELS {} {} {}",
+            &line["TILE ".len()..],
+            counts.paths,
+            counts.rays
+        )?;
+        for j in 0..image.height() {
+            for i in 0..image.width() {
+                let c = image.pixel(i, j);
+                for value in [c.x, c.y, c.z] {
+                    reply.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        writer.write_all(&reply)?;
+        tiles += 1;
+    }
+}
+
+// The contents of the scene file that follow the job's arguments, or `None`
+// for a built-in scene. The file arrives a buffer at a time, so a bad size
+// cannot make the worker allocate more than it receives.
+fn read_scene(reader: &mut BufReader<TcpStream>) -> io::Result<Option<String>> {
+    let line = read_line(reader)?;
+    let size: u64 = line
+        .strip_prefix("SCENE ")
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| protocol_error(&line))?;
+    if size == 0 {
+        return Ok(None);
+    }
+
+    let mut source = String::new();
+    reader.take(size).read_to_string(&mut source)?;
+    if source.len() as u64 != size {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed",
+        ));
+    }
+    Ok(Some(source))
+}
+
+// The scene and render options for the coordinator's arguments and scene
+// file; the worker's own thread count wins over the coordinator's.
+fn job(
+    args: Vec<String>,
+    source: Option<&str>,
+    threads: Option<usize>,
+) -> Result<(raytracing::Scene, RenderOptions), String> {
+    let options = match cli::parse(args) {
+        Ok(Command::Render(options)) => options,
+        Ok(_) => return Err(String::from("the job is not a render")),
+        Err(err) => return Err(err.to_string()),
+    };
+    let scene = crate::prepare_scene(&options, source)?;
+
+    let mut render_options = RenderOptions {
+        seed: options.seed,
+        ..Default::default()
+    };
+    if let Some(threads) = threads {
+        render_options.threads = threads;
+    }
+    Ok((scene, render_options))
+}
+
+// Renders a tile on another thread, sending `BUSY` to the coordinator every
+// `HEARTBEAT_INTERVAL` until it is done.
+fn render_with_heartbeat(
+    camera: &Camera,
+    world: &HittableList,
+    options: &RenderOptions,
+    writer: &mut TcpStream,
+) -> io::Result<Image> {
+    thread::scope(|scope| {
+        let (done, rendered) = mpsc::channel();
+        scope.spawn(move || {
+            let _ = done.send(camera.render(world, options));
+        });
+        loop {
+            match rendered.recv_timeout(HEARTBEAT_INTERVAL) {
+                Ok(image) => return Ok(image),
+                Err(RecvTimeoutError::Timeout) => writeln!(writer, "BUSY")?,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::other("render thread panicked"))
+                }
+            }
+        }
+    })
+}
+
+fn parse_tile(line: &str) -> Option<Tile> {
+    let values: Vec<usize> = line
+        .strip_prefix("TILE ")?
+        .split(' ')
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    match values[..] {
+        [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => Some(Tile { x0, y0, x1, y1 }),
+        _ => None,
+    }
+}
+
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed",
+        ));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn protocol_error(line: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("unexpected message `{line}`"),
+    )
+}
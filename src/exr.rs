@@ -0,0 +1,161 @@
+use std::io::{self, Write};
+
+use crate::image::Region;
+
+// A minimal OpenEXR writer: scanline images without compression, with any
+// number of 32-bit float or unsigned int channels. Channel names follow the
+// EXR convention of `layer.channel`, such as `albedo.R`; plain `R`, `G` and
+// `B` are the default layer that viewers show.
+
+pub enum Samples {
+    Float(Vec<f32>),
+    Uint(Vec<u32>),
+}
+
+pub struct Channel {
+    pub name: String,
+    pub samples: Samples,
+}
+
+impl Channel {
+    pub fn float(name: impl Into<String>, samples: Vec<f32>) -> Self {
+        Channel {
+            name: name.into(),
+            samples: Samples::Float(samples),
+        }
+    }
+
+    pub fn uint(name: impl Into<String>, samples: Vec<u32>) -> Self {
+        Channel {
+            name: name.into(),
+            samples: Samples::Uint(samples),
+        }
+    }
+
+    fn pixel_type(&self) -> i32 {
+        match self.samples {
+            Samples::Uint(_) => 0,
+            Samples::Float(_) => 2,
+        }
+    }
+
+    fn write_row(&self, output: &mut Vec<u8>, row: usize, width: usize) {
+        let range = row * width..(row + 1) * width;
+        match &self.samples {
+            Samples::Float(values) => {
+                for value in &values[range] {
+                    output.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+            Samples::Uint(values) => {
+                for value in &values[range] {
+                    output.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+// Writes a `width` x `height` image whose channels hold one sample per
+// pixel, row by row from the top. A crop's `region` becomes the data window
+// within a display window of the full frame.
+pub fn write(
+    output: &mut dyn Write,
+    width: usize,
+    height: usize,
+    region: Option<Region>,
+    mut channels: Vec<Channel>,
+) -> io::Result<()> {
+    // EXR files list and store their channels sorted by name.
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    let region = region.unwrap_or(Region {
+        x: 0,
+        y: 0,
+        full_width: width,
+        full_height: height,
+    });
+
+    let mut header = Vec::new();
+    header.extend_from_slice(&[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    let mut list = Vec::new();
+    for channel in &channels {
+        list.extend_from_slice(channel.name.as_bytes());
+        list.push(0);
+        list.extend_from_slice(&channel.pixel_type().to_le_bytes());
+        // pLinear and three reserved bytes, then the x and y sampling.
+        list.extend_from_slice(&[0, 0, 0, 0]);
+        list.extend_from_slice(&1i32.to_le_bytes());
+        list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    list.push(0);
+    attribute(&mut header, "channels", "chlist", &list);
+
+    attribute(&mut header, "compression", "compression", &[0]);
+    let window = |x: usize, y: usize, w: usize, h: usize| {
+        [x, y, x + w - 1, y + h - 1]
+            .iter()
+            .flat_map(|v| (*v as i32).to_le_bytes())
+            .collect::<Vec<u8>>()
+    };
+    attribute(
+        &mut header,
+        "dataWindow",
+        "box2i",
+        &window(region.x, region.y, width, height),
+    );
+    attribute(
+        &mut header,
+        "displayWindow",
+        "box2i",
+        &window(0, 0, region.full_width, region.full_height),
+    );
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    );
+    header.push(0);
+
+    // Every row is its own block: the row number, the data size, and then
+    // the row of each channel in turn. The offset table before the blocks
+    // says where each one starts.
+    let row_size = channels.len() * width * 4;
+    let block_size = 8 + row_size;
+    let table_end = header.len() + height * 8;
+    output.write_all(&header)?;
+    for row in 0..height {
+        let offset = (table_end + row * block_size) as u64;
+        output.write_all(&offset.to_le_bytes())?;
+    }
+
+    let mut block = Vec::with_capacity(block_size);
+    for row in 0..height {
+        block.clear();
+        block.extend_from_slice(&((region.y + row) as i32).to_le_bytes());
+        block.extend_from_slice(&(row_size as i32).to_le_bytes());
+        for channel in &channels {
+            channel.write_row(&mut block, row, width);
+        }
+        output.write_all(&block)?;
+    }
+    Ok(())
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
@@ -0,0 +1,323 @@
+use std::{
+    f64::consts::PI,
+    io::{self, Error, ErrorKind, Read, Write},
+};
+
+use crate::{color::Color, image::Image};
+
+const MIN_WEIGHT: f64 = 1e-6;
+
+// Pixel reconstruction filters. Each sample is spread over every pixel whose
+// center lies within `radius` pixels of it on both axes, weighted by the
+// filter; a pixel's value is the weighted average of the samples it
+// received. A box of radius 0.5 gives the plain per-pixel average.
+//
+// The filters are separable: the weight is f(dx) * f(dy).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Filter {
+    Box { radius: f64 },
+    Tent { radius: f64 },
+    // Falls off as exp(-alpha * x^2), shifted to reach zero at the radius.
+    Gaussian { radius: f64, alpha: f64 },
+    // Mitchell-Netravali cubic; b = c = 1/3 is the recommended balance
+    // between blurring and ringing.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    // Sinc windowed by a wider sinc; `tau` is the number of lobes kept.
+    Lanczos { radius: f64, tau: f64 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box { radius }
+            | Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. }
+            | Filter::Lanczos { radius, .. } => radius,
+        }
+    }
+
+    // How many pixels away from its own a sample can reach.
+    pub fn reach(&self) -> usize {
+        f64::ceil(self.radius() - 0.5).max(0.0) as usize
+    }
+
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = f64::abs(x);
+        match *self {
+            Filter::Box { .. } => 1.0,
+            Filter::Tent { radius } => f64::max(0.0, radius - x),
+            Filter::Gaussian { radius, alpha } => f64::max(
+                0.0,
+                f64::exp(-alpha * x * x) - f64::exp(-alpha * radius * radius),
+            ),
+            Filter::Mitchell { radius, b, c } => {
+                let x = 2.0 * x / radius;
+                if x > 2.0 {
+                    0.0
+                } else if x > 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Filter::Lanczos { radius, tau } => {
+                if x > radius {
+                    0.0
+                } else {
+                    sinc(x) * sinc(x / tau)
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x < 1e-5 {
+        return 1.0;
+    }
+    let x = PI * x;
+    x.sin() / x
+}
+
+// Running sums of the luminance of the samples taken in a pixel, from which
+// adaptive sampling estimates its noise. Sums rather than a running mean, so
+// that films merge by adding.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PixelStats {
+    pub samples: u32,
+    pub sum: f64,
+    pub sum_sq: f64,
+}
+
+impl PixelStats {
+    pub fn add(&mut self, luminance: f64) {
+        self.samples += 1;
+        self.sum += luminance;
+        self.sum_sq += luminance * luminance;
+    }
+
+    pub fn merge(&mut self, other: &PixelStats) {
+        self.samples += other.samples;
+        self.sum += other.sum;
+        self.sum_sq += other.sum_sq;
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.sum / self.samples as f64
+    }
+
+    // The standard error of the mean; infinite until there are two samples.
+    pub fn standard_error(&self) -> f64 {
+        if self.samples < 2 {
+            return f64::INFINITY;
+        }
+        let n = self.samples as f64;
+        let variance = f64::max(0.0, (self.sum_sq - self.sum * self.sum / n) / (n - 1.0));
+        f64::sqrt(variance / n)
+    }
+}
+
+// Accumulates filtered samples for a band of rows starting at `y0`. A film
+// for a single row's samples covers only the rows its filter reaches, and is
+// merged into the full-frame film afterwards. The film also keeps the
+// `PixelStats` of the samples taken in each pixel.
+#[derive(Debug, Clone)]
+pub struct Film {
+    width: usize,
+    height: usize,
+    y0: usize,
+    rows: usize,
+    sum: Vec<Color>,
+    weight: Vec<f64>,
+    stats: Vec<PixelStats>,
+}
+
+impl Film {
+    pub fn new(width: usize, height: usize) -> Self {
+        Film::band(width, height, 0, height)
+    }
+
+    // A film for rows `y0..y0 + rows` of a `width` x `height` frame.
+    pub fn band(width: usize, height: usize, y0: usize, rows: usize) -> Self {
+        Film {
+            width,
+            height,
+            y0,
+            rows,
+            sum: vec![Color::default(); width * rows],
+            weight: vec![0.0; width * rows],
+            stats: vec![PixelStats::default(); width * rows],
+        }
+    }
+
+    // The rows a sample in row `j` can reach under `filter`.
+    pub fn band_for_row(width: usize, height: usize, j: usize, filter: &Filter) -> Self {
+        let reach = filter.reach();
+        let y0 = j.saturating_sub(reach);
+        let y1 = usize::min(j + reach + 1, height);
+        Film::band(width, height, y0, y1 - y0)
+    }
+
+    // Adds a sample taken at continuous film position (x, y), where pixel
+    // (i, j) covers [i, i + 1) x [j, j + 1). The filter's reach is half-open
+    // in the same way: a sample exactly `radius` from two pixel centers
+    // counts only for the later one, so that a box of radius 0.5 puts every
+    // sample in exactly one pixel.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color, filter: &Filter) {
+        let radius = filter.radius();
+        let x_min = (f64::floor(x - 0.5 - radius) + 1.0).max(0.0) as usize;
+        let x_max = f64::floor(x - 0.5 + radius).min(self.width as f64 - 1.0);
+        let y_min = (f64::floor(y - 0.5 - radius) + 1.0).max(self.y0 as f64) as usize;
+        let y_max = f64::floor(y - 0.5 + radius).min((self.y0 + self.rows) as f64 - 1.0);
+        if x_max < 0.0 || y_max < 0.0 {
+            return;
+        }
+
+        for j in y_min..=y_max as usize {
+            for i in x_min..=x_max as usize {
+                let w = filter.evaluate(i as f64 + 0.5 - x, j as f64 + 0.5 - y);
+                if w == 0.0 {
+                    continue;
+                }
+                let index = (j - self.y0) * self.width + i;
+                self.sum[index] += w * color;
+                self.weight[index] += w;
+            }
+        }
+    }
+
+    // Counts a sample taken in pixel (i, j), whose color had `luminance`.
+    pub fn record(&mut self, i: usize, j: usize, luminance: f64) {
+        self.stats[(j - self.y0) * self.width + i].add(luminance);
+    }
+
+    pub fn pixel_stats(&self, i: usize, j: usize) -> PixelStats {
+        self.stats[(j - self.y0) * self.width + i]
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn merge(&mut self, other: &Film) {
+        for r in 0..other.rows {
+            let j = other.y0 + r;
+            if j < self.y0 || j >= self.y0 + self.rows {
+                continue;
+            }
+            for i in 0..self.width {
+                let src = r * other.width + i;
+                let dst = (j - self.y0) * self.width + i;
+                self.sum[dst] += other.sum[src];
+                self.weight[dst] += other.weight[src];
+                self.stats[dst].merge(&other.stats[src]);
+            }
+        }
+    }
+
+    // Writes the whole film losslessly, in little-endian binary: the size,
+    // then each pixel's weighted sum, weight and statistics.
+    pub fn write(&self, output: &mut dyn Write) -> io::Result<()> {
+        if self.y0 != 0 || self.rows != self.height {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "only a full-frame film can be written",
+            ));
+        }
+
+        output.write_all(&(self.width as u64).to_le_bytes())?;
+        output.write_all(&(self.height as u64).to_le_bytes())?;
+        for index in 0..self.width * self.height {
+            let (sum, stats) = (self.sum[index], self.stats[index]);
+            for value in [sum.x, sum.y, sum.z, self.weight[index]] {
+                output.write_all(&value.to_le_bytes())?;
+            }
+            output.write_all(&stats.samples.to_le_bytes())?;
+            output.write_all(&stats.sum.to_le_bytes())?;
+            output.write_all(&stats.sum_sq.to_le_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    // Reads a film written by `write`, which must be `width` x `height`. The
+    // size is checked before the film is allocated, so a corrupt file cannot
+    // ask for more memory than the render itself needs.
+    pub fn read(input: &mut dyn Read, width: usize, height: usize) -> io::Result<Film> {
+        let file_width = u64::from_le_bytes(read_array(input)?);
+        let file_height = u64::from_le_bytes(read_array(input)?);
+        if (file_width, file_height) != (width as u64, height as u64) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("the film is {file_width}x{file_height}, not {width}x{height}"),
+            ));
+        }
+
+        let mut film = Film::new(width, height);
+        for index in 0..width * height {
+            let mut values = [0.0; 4];
+            for value in values.iter_mut() {
+                *value = f64::from_le_bytes(read_array(input)?);
+            }
+            film.sum[index] = Color::new(values[0], values[1], values[2]);
+            film.weight[index] = values[3];
+            film.stats[index] = PixelStats {
+                samples: u32::from_le_bytes(read_array(input)?),
+                sum: f64::from_le_bytes(read_array(input)?),
+                sum_sq: f64::from_le_bytes(read_array(input)?),
+            };
+        }
+
+        Ok(film)
+    }
+
+    // The filtered image, scaled by `exposure`. Pixels that received no
+    // weight are black, and so are those whose weights, under filters with
+    // negative lobes, cancel out to about zero or less, where dividing would
+    // blow the color up or flip its sign.
+    pub fn to_image(&self, exposure: f64) -> Image {
+        let mut image = Image::new(self.width, self.height);
+        for r in 0..self.rows {
+            for i in 0..self.width {
+                let index = r * self.width + i;
+                if self.weight[index] > MIN_WEIGHT {
+                    let color = self.sum[index] * (exposure / self.weight[index]);
+                    image.set_pixel(i, self.y0 + r, color);
+                }
+            }
+        }
+
+        image
+    }
+}
+
+fn read_array<const N: usize>(input: &mut dyn Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
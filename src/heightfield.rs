@@ -0,0 +1,321 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    image::Image,
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+// A terrain surface sampled on a regular grid in the xz plane. Each grid cell
+// is split into two triangles; rays walk the cells they cross with a 2D DDA,
+// so only the cells under the ray are ever tested.
+pub struct Heightfield {
+    corner: Vec3,
+    cell_x: f64,
+    cell_z: f64,
+    nx: usize,
+    nz: usize,
+    heights: Vec<f64>,
+    normals: Option<Vec<Vec3>>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+struct TriangleHit {
+    t: f64,
+    b1: f64,
+    b2: f64,
+    normal: Vec3,
+}
+
+impl Heightfield {
+    // `grid[z][x]` holds heights in world units above `corner`; the grid is
+    // stretched over `size_x` by `size_z`.
+    pub fn new(
+        grid: Vec<Vec<f64>>,
+        corner: Vec3,
+        size_x: f64,
+        size_z: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let nz = grid.len();
+        let nx = grid.iter().map(|row| row.len()).min().unwrap_or(0);
+        let heights: Vec<f64> = grid
+            .into_iter()
+            .flat_map(|row| row.into_iter().take(nx))
+            .collect();
+
+        let (min_h, max_h) = heights
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &h| {
+                (f64::min(lo, h), f64::max(hi, h))
+            });
+        let bbox = if heights.is_empty() {
+            Aabb::from_points(corner, corner)
+        } else {
+            Aabb::from_points(
+                corner + Vec3::new(0.0, min_h, 0.0),
+                corner + Vec3::new(size_x, max_h, size_z),
+            )
+        };
+
+        Heightfield {
+            corner,
+            cell_x: size_x / (nx.max(2) - 1) as f64,
+            cell_z: size_z / (nz.max(2) - 1) as f64,
+            nx,
+            nz,
+            heights,
+            normals: None,
+            mat,
+            bbox,
+        }
+    }
+
+    // Uses the image's luminance as height: black maps to `corner.y` and
+    // white to `corner.y + size.y`. Rows of the image run along +z.
+    pub fn from_image(image: &Image, corner: Vec3, size: Vec3, mat: Arc<dyn Material>) -> Self {
+        let grid = (0..image.height())
+            .map(|y| {
+                (0..image.width())
+                    .map(|x| {
+                        let c = image.pixel(x, y);
+                        size.y * (0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Heightfield::new(grid, corner, size.x, size.z, mat)
+    }
+
+    // Interpolates per-vertex normals across each triangle instead of using
+    // the flat facet normal.
+    pub fn with_smooth_normals(mut self) -> Self {
+        let mut normals = Vec::with_capacity(self.heights.len());
+        for z in 0..self.nz {
+            for x in 0..self.nx {
+                let (x0, x1) = (x.saturating_sub(1), (x + 1).min(self.nx - 1));
+                let (z0, z1) = (z.saturating_sub(1), (z + 1).min(self.nz - 1));
+                let dx =
+                    (self.height(x1, z) - self.height(x0, z)) / ((x1 - x0) as f64 * self.cell_x);
+                let dz =
+                    (self.height(x, z1) - self.height(x, z0)) / ((z1 - z0) as f64 * self.cell_z);
+                normals.push(Vec3::unit_vector(Vec3::new(-dx, 1.0, -dz)));
+            }
+        }
+        self.normals = Some(normals);
+
+        self
+    }
+
+    fn height(&self, x: usize, z: usize) -> f64 {
+        self.heights[z * self.nx + x]
+    }
+
+    fn vertex(&self, x: usize, z: usize) -> Vec3 {
+        self.corner
+            + Vec3::new(
+                x as f64 * self.cell_x,
+                self.height(x, z),
+                z as f64 * self.cell_z,
+            )
+    }
+
+    fn vertex_normal(&self, x: usize, z: usize) -> Option<Vec3> {
+        self.normals.as_ref().map(|n| n[z * self.nx + x])
+    }
+
+    fn hit_triangle(
+        r: &Ray,
+        v0: Vec3,
+        v1: Vec3,
+        v2: Vec3,
+        interval: Interval,
+    ) -> Option<TriangleHit> {
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+        let pvec = r.dir.cross(e2);
+        let det = e1.dot(pvec);
+        if f64::abs(det) < 1e-12 {
+            return None;
+        }
+
+        let inv_det = 1.0 / det;
+        let tvec = r.origin - v0;
+        let b1 = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let qvec = tvec.cross(e1);
+        let b2 = r.dir.dot(qvec) * inv_det;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(qvec) * inv_det;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let mut normal = e1.cross(e2);
+        if normal.y < 0.0 {
+            normal = -normal;
+        }
+
+        Some(TriangleHit {
+            t,
+            b1,
+            b2,
+            normal: Vec3::unit_vector(normal),
+        })
+    }
+
+    fn hit_cell(
+        &self,
+        r: &Ray,
+        x: usize,
+        z: usize,
+        interval: Interval,
+        rec: &mut HitRecord,
+    ) -> bool {
+        let corners = [(x, z), (x + 1, z), (x + 1, z + 1), (x, z + 1)];
+        let triangles = [
+            [corners[0], corners[1], corners[2]],
+            [corners[0], corners[2], corners[3]],
+        ];
+
+        let mut closest = interval.max;
+        let mut hit_anything = false;
+        for tri in triangles {
+            let [a, b, c] = tri.map(|(vx, vz)| self.vertex(vx, vz));
+            let Some(hit) =
+                Heightfield::hit_triangle(r, a, b, c, Interval::new(interval.min, closest))
+            else {
+                continue;
+            };
+
+            closest = hit.t;
+            hit_anything = true;
+
+            let normal = match tri.map(|(vx, vz)| self.vertex_normal(vx, vz)) {
+                [Some(na), Some(nb), Some(nc)] => {
+                    Vec3::unit_vector((1.0 - hit.b1 - hit.b2) * na + hit.b1 * nb + hit.b2 * nc)
+                }
+                _ => hit.normal,
+            };
+
+            rec.t = hit.t;
+            rec.p = r.at(hit.t);
+            let size = self.bbox_size();
+            rec.u = (rec.p.x - self.corner.x) / size.0;
+            rec.v = (rec.p.z - self.corner.z) / size.1;
+            rec.set_face_normal(r, &normal);
+            rec.mat = self.mat.clone();
+        }
+
+        hit_anything
+    }
+
+    fn bbox_size(&self) -> (f64, f64) {
+        (
+            self.cell_x * (self.nx - 1) as f64,
+            self.cell_z * (self.nz - 1) as f64,
+        )
+    }
+}
+
+impl Hittable for Heightfield {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Heightfield);
+        if self.nx < 2 || self.nz < 2 {
+            return false;
+        }
+        let Some(range) = self.bbox.clip(r, interval) else {
+            return false;
+        };
+
+        let cells_x = self.nx - 1;
+        let cells_z = self.nz - 1;
+        let entry = r.at(range.min);
+        let gx = (entry.x - self.corner.x) / self.cell_x;
+        let gz = (entry.z - self.corner.z) / self.cell_z;
+        let mut x = (gx.floor().max(0.0) as usize).min(cells_x - 1);
+        let mut z = (gz.floor().max(0.0) as usize).min(cells_z - 1);
+
+        let axis = |cell: usize, origin: f64, dir: f64, size: f64, corner: f64| {
+            if dir > 0.0 {
+                (
+                    1,
+                    (corner + (cell + 1) as f64 * size - origin) / dir,
+                    size / dir,
+                )
+            } else if dir < 0.0 {
+                (
+                    -1,
+                    (corner + cell as f64 * size - origin) / dir,
+                    -size / dir,
+                )
+            } else {
+                (0, f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (step_x, mut next_x, delta_x) =
+            axis(x, r.origin.x, r.dir.x, self.cell_x, self.corner.x);
+        let (step_z, mut next_z, delta_z) =
+            axis(z, r.origin.z, r.dir.z, self.cell_z, self.corner.z);
+
+        let mut t_enter = range.min;
+        loop {
+            let t_exit = f64::min(f64::min(next_x, next_z), range.max);
+
+            // Skip cells whose height range the ray segment cannot reach.
+            let (y0, y1) = (r.at(t_enter).y, r.at(t_exit).y);
+            let cell_heights = [(x, z), (x + 1, z), (x, z + 1), (x + 1, z + 1)]
+                .map(|(vx, vz)| self.corner.y + self.height(vx, vz));
+            let cell_min = cell_heights.iter().copied().fold(f64::INFINITY, f64::min);
+            let cell_max = cell_heights
+                .iter()
+                .copied()
+                .fold(f64::NEG_INFINITY, f64::max);
+            let ray_min = f64::min(y0, y1);
+            let ray_max = f64::max(y0, y1);
+            if ray_max >= cell_min - 1e-9
+                && ray_min <= cell_max + 1e-9
+                && self.hit_cell(r, x, z, interval, rec)
+            {
+                return true;
+            }
+
+            if t_exit >= range.max {
+                return false;
+            }
+
+            if next_x < next_z {
+                if (step_x < 0 && x == 0) || (step_x > 0 && x + 1 >= cells_x) {
+                    return false;
+                }
+                x = (x as isize + step_x) as usize;
+                t_enter = next_x;
+                next_x += delta_x;
+            } else {
+                if (step_z < 0 && z == 0) || (step_z > 0 && z + 1 >= cells_z) {
+                    return false;
+                }
+                z = (z as isize + step_z) as usize;
+                t_enter = next_z;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
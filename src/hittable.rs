@@ -1,26 +1,88 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use crate::{
+    aabb::{self, Aabb},
     interval::Interval,
     material::{Material, MaterialZST},
     ray::Ray,
     vec3::Vec3,
 };
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     #[allow(dead_code, unused_variables)]
     fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
         false
     }
+
+    fn bounding_box(&self) -> Aabb;
+
+    // Every interval of the ray's full line that lies inside the object, in
+    // ascending order. The default walks successive surface crossings with
+    // `hit` and pairs entries with exits, which is correct for any closed
+    // surface; a span that is still open at either end is bounded by a record
+    // at infinity.
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        let mut spans = Vec::new();
+        let mut enter: Option<HitRecord> = None;
+        let mut depth = 0;
+        let mut t_min = f64::NEG_INFINITY;
+
+        for _ in 0..MAX_CROSSINGS {
+            let mut rec = HitRecord::default();
+            if !self.hit(r, Interval::new(t_min, f64::INFINITY), &mut rec) {
+                break;
+            }
+            t_min = rec.t + 1e-9 * f64::max(1.0, f64::abs(rec.t));
+
+            if rec.front_face {
+                depth += 1;
+                if depth == 1 {
+                    enter = Some(rec);
+                }
+            } else if depth == 0 {
+                spans.push(Span {
+                    enter: HitRecord::at_infinity(f64::NEG_INFINITY),
+                    exit: rec,
+                });
+            } else {
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(enter) = enter.take() {
+                        spans.push(Span { enter, exit: rec });
+                    }
+                }
+            }
+        }
+
+        if let Some(enter) = enter {
+            spans.push(Span {
+                enter,
+                exit: HitRecord::at_infinity(f64::INFINITY),
+            });
+        }
+
+        spans
+    }
+}
+
+const MAX_CROSSINGS: usize = 64;
+
+#[derive(Clone)]
+pub struct Span {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
 }
 
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Vec3,
     pub normal: Vec3,
-    pub mat: Rc<dyn Material>,
+    pub mat: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
     pub front_face: bool,
+    pub object_id: u32,
 }
 
 impl Default for HitRecord {
@@ -28,14 +90,24 @@ impl Default for HitRecord {
         Self {
             p: Default::default(),
             normal: Default::default(),
-            mat: Rc::new(MaterialZST),
+            mat: Arc::new(MaterialZST),
             t: Default::default(),
+            u: Default::default(),
+            v: Default::default(),
             front_face: Default::default(),
+            object_id: 0,
         }
     }
 }
 
 impl HitRecord {
+    pub fn at_infinity(t: f64) -> Self {
+        HitRecord {
+            t,
+            ..Default::default()
+        }
+    }
+
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = r.dir.dot(*outward_normal) < 0.0;
         self.normal = if self.front_face {
@@ -47,23 +119,71 @@ impl HitRecord {
 }
 
 pub struct HittableList {
-    pub list: Vec<Rc<dyn Hittable>>,
+    pub list: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
 }
 
 impl HittableList {
     pub fn new() -> Self {
-        HittableList { list: Vec::new() }
+        HittableList {
+            list: Vec::new(),
+            bbox: aabb::EMPTY,
+        }
     }
 
-    pub fn _clear(&mut self) {
+    pub fn clear(&mut self) {
         self.list.clear();
+        self.bbox = aabb::EMPTY;
     }
 
-    pub fn add(&mut self, object: Rc<dyn Hittable>) {
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
         self.list.push(object);
     }
 }
 
+// Gives an object an ID, which its hits carry in `HitRecord::object_id`
+// for the object ID AOV.
+pub struct Labeled {
+    object: Arc<dyn Hittable>,
+    id: u32,
+}
+
+impl Labeled {
+    pub fn new(object: Arc<dyn Hittable>, id: u32) -> Self {
+        Labeled { object, id }
+    }
+}
+
+impl Hittable for Labeled {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        if !self.object.hit(r, interval, rec) {
+            return false;
+        }
+        rec.object_id = self.id;
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        let mut spans = self.object.spans(r);
+        for span in &mut spans {
+            span.enter.object_id = self.id;
+            span.exit.object_id = self.id;
+        }
+        spans
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        HittableList::new()
+    }
+}
+
 impl Hittable for HittableList {
     fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
         let mut temp_rec = HitRecord::default();
@@ -71,6 +191,8 @@ impl Hittable for HittableList {
         let mut closest = interval.max;
 
         for obj in self.list.clone() {
+            // Only labeled objects set an ID; others must not inherit one.
+            temp_rec.object_id = 0;
             if obj.hit(r, Interval::new(interval.min, closest), &mut temp_rec) {
                 hit_anything = true;
                 closest = temp_rec.t;
@@ -80,4 +202,8 @@ impl Hittable for HittableList {
 
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 }
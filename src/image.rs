@@ -0,0 +1,512 @@
+use std::{
+    fmt, fs,
+    io::{self, Error, ErrorKind, Write},
+    ops::Range,
+    path::Path,
+    str::FromStr,
+};
+
+use crate::{
+    color::{color_to_bytes, write_color, Color},
+    exr::{self, Channel},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    // Gamma-corrected 8-bit ASCII PPM.
+    Ppm,
+    // Gamma-corrected 8-bit PNG.
+    Png,
+    // Linear 32-bit float PFM, for HDR post-processing.
+    Pfm,
+    // Linear 32-bit float OpenEXR, uncompressed.
+    Exr,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: impl AsRef<Path>) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        extension.to_ascii_lowercase().parse().ok()
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ppm" => Ok(ImageFormat::Ppm),
+            "png" => Ok(ImageFormat::Png),
+            "pfm" => Ok(ImageFormat::Pfm),
+            "exr" => Ok(ImageFormat::Exr),
+            _ => Err(format!(
+                "unknown image format `{s}` (expected ppm, png, pfm or exr)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Png => "png",
+            ImageFormat::Pfm => "pfm",
+            ImageFormat::Exr => "exr",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Where a crop sits in the full frame it was cut from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub full_width: usize,
+    pub full_height: usize,
+}
+
+// Rendered images hold linear radiance. Pixels loaded from files are kept
+// exactly as stored, with PGM and PPM values normalised to [0, 1]; no gamma
+// conversion is applied.
+//
+// A crop of a larger image carries its `Region`, which PPM and PFM files
+// record in a header comment.
+#[derive(Debug, Clone)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    region: Option<Region>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        Image {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+            region: None,
+        }
+    }
+
+    // Reads binary or ASCII PGM/PPM files (P2, P3, P5 and P6) and grayscale
+    // or color PFM files.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Image> {
+        let data = fs::read(path)?;
+        if data.starts_with(b"PF") || data.starts_with(b"Pf") {
+            Image::decode_pfm(&data)
+        } else {
+            Image::decode_pnm(&data)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> Color {
+        let x = x.min(self.width.saturating_sub(1));
+        let y = y.min(self.height.saturating_sub(1));
+        self.pixels
+            .get(y * self.width + x)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn region(&self) -> Option<Region> {
+        self.region
+    }
+
+    pub(crate) fn set_region(&mut self, region: Option<Region>) {
+        self.region = region;
+    }
+
+    // The pixels in columns `x` and rows `y`.
+    pub fn crop(&self, x: Range<usize>, y: Range<usize>) -> Image {
+        let mut image = Image::new(x.len(), y.len());
+        for (cy, j) in y.clone().enumerate() {
+            for (cx, i) in x.clone().enumerate() {
+                image.set_pixel(cx, cy, self.pixel(i, j));
+            }
+        }
+        image.region = Some(Region {
+            x: x.start,
+            y: y.start,
+            full_width: self.width,
+            full_height: self.height,
+        });
+
+        image
+    }
+
+    // A crop put back in place in an otherwise black full frame; other
+    // images are returned as they are.
+    pub fn full_frame(&self) -> Image {
+        match self.region {
+            Some(region) => {
+                let mut image = Image::new(region.full_width, region.full_height);
+                image.paste(self);
+                image
+            }
+            None => self.clone(),
+        }
+    }
+
+    // Copies a crop of an image this size into its place.
+    pub fn paste(&mut self, crop: &Image) {
+        let region = crop.region.unwrap_or(Region {
+            x: 0,
+            y: 0,
+            full_width: crop.width,
+            full_height: crop.height,
+        });
+        for j in 0..crop.height {
+            for i in 0..crop.width {
+                self.set_pixel(region.x + i, region.y + j, crop.pixel(i, j));
+            }
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, format: ImageFormat) -> io::Result<()> {
+        let mut file = io::BufWriter::new(fs::File::create(path)?);
+        self.write(&mut file, format)?;
+        file.flush()
+    }
+
+    pub fn write(&self, output: &mut dyn Write, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => self.write_ppm(output),
+            ImageFormat::Png => self.write_png(output),
+            ImageFormat::Pfm => self.write_pfm(output),
+            ImageFormat::Exr => exr::write(
+                output,
+                self.width,
+                self.height,
+                self.region,
+                self.exr_channels("", ["R", "G", "B"]),
+            ),
+        }
+    }
+
+    fn write_ppm(&self, output: &mut dyn Write) -> io::Result<()> {
+        writeln!(output, "P3")?;
+        if let Some(region) = self.region {
+            writeln!(
+                output,
+                "# region {} {} {} {}",
+                region.x, region.y, region.full_width, region.full_height
+            )?;
+        }
+        writeln!(output, "{} {}\n255", self.width, self.height)?;
+        for pixel in &self.pixels {
+            write_color(output, pixel)?;
+        }
+        Ok(())
+    }
+
+    // PNG written with uncompressed (stored) deflate blocks, which keeps the
+    // encoder tiny at the cost of file size.
+    fn write_png(&self, output: &mut dyn Write) -> io::Result<()> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            for pixel in row {
+                raw.extend_from_slice(&color_to_bytes(pixel));
+            }
+        }
+
+        let mut zlib = vec![0x78, 0x01];
+        let mut blocks = raw.chunks(0xffff).peekable();
+        if blocks.peek().is_none() {
+            zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+        }
+        while let Some(block) = blocks.next() {
+            let last = blocks.peek().is_none();
+            let len = block.len() as u16;
+            zlib.push(last as u8);
+            zlib.extend_from_slice(&len.to_le_bytes());
+            zlib.extend_from_slice(&(!len).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        output.write_all(b"\x89PNG\r\n\x1a\n")?;
+        write_png_chunk(output, b"IHDR", &header)?;
+        write_png_chunk(output, b"IDAT", &zlib)?;
+        write_png_chunk(output, b"IEND", &[])
+    }
+
+    fn write_pfm(&self, output: &mut dyn Write) -> io::Result<()> {
+        writeln!(output, "PF")?;
+        if let Some(region) = self.region {
+            writeln!(
+                output,
+                "# region {} {} {} {}",
+                region.x, region.y, region.full_width, region.full_height
+            )?;
+        }
+        // A negative scale marks little-endian data; rows run bottom to top.
+        writeln!(output, "{} {}\n-1.0", self.width, self.height)?;
+        for row in self.pixels.chunks(self.width.max(1)).rev() {
+            for pixel in row {
+                for c in [pixel.x, pixel.y, pixel.z] {
+                    output.write_all(&(c as f32).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // The pixels as three EXR channels named `channels`, in `layer` if it
+    // is not empty.
+    pub(crate) fn exr_channels(&self, layer: &str, channels: [&str; 3]) -> Vec<Channel> {
+        let prefix = if layer.is_empty() {
+            String::new()
+        } else {
+            format!("{layer}.")
+        };
+        let component = |f: fn(&Color) -> f64| self.pixels.iter().map(|c| f(c) as f32).collect();
+        vec![
+            Channel::float(format!("{prefix}{}", channels[0]), component(|c| c.x)),
+            Channel::float(format!("{prefix}{}", channels[1]), component(|c| c.y)),
+            Channel::float(format!("{prefix}{}", channels[2]), component(|c| c.z)),
+        ]
+    }
+
+    fn decode_pnm(data: &[u8]) -> io::Result<Image> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+        let mut pos = 0;
+        let magic = next_token(data, &mut pos).ok_or_else(|| invalid("missing PNM header"))?;
+        let (channels, binary) = match magic {
+            b"P2" => (1, false),
+            b"P3" => (3, false),
+            b"P5" => (1, true),
+            b"P6" => (3, true),
+            _ => return Err(invalid("unsupported image format, expected PGM or PPM")),
+        };
+
+        let mut header = [0usize; 3];
+        for value in header.iter_mut() {
+            *value = next_token(data, &mut pos)
+                .and_then(|t| std::str::from_utf8(t).ok())
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid("malformed PNM header"))?;
+        }
+        let [width, height, max_value] = header;
+        if max_value == 0 || max_value > 65535 {
+            return Err(invalid("PNM maximum value out of range"));
+        }
+
+        let count = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels))
+            .ok_or_else(|| invalid("PNM image size out of range"))?;
+        let samples: Vec<f64> = if binary {
+            // Exactly one whitespace byte separates the header from the data.
+            pos += 1;
+            let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+            let raw = count
+                .checked_mul(bytes_per_sample)
+                .and_then(|n| n.checked_add(pos))
+                .and_then(|end| data.get(pos..end))
+                .ok_or_else(|| invalid("truncated PNM pixel data"))?;
+            raw.chunks(bytes_per_sample)
+                .map(|c| match c {
+                    [hi, lo] => u16::from_be_bytes([*hi, *lo]) as f64,
+                    _ => c[0] as f64,
+                })
+                .collect()
+        } else {
+            // Every sample takes at least a byte, so a header that promises
+            // more samples than there are bytes left is cut short.
+            if count > data.len() - pos {
+                return Err(invalid("truncated PNM pixel data"));
+            }
+            let mut samples = Vec::with_capacity(count);
+            for _ in 0..count {
+                let value = next_token(data, &mut pos)
+                    .and_then(|t| std::str::from_utf8(t).ok())
+                    .and_then(|t| t.parse::<f64>().ok())
+                    .ok_or_else(|| invalid("truncated PNM pixel data"))?;
+                samples.push(value);
+            }
+            samples
+        };
+
+        let scale = 1.0 / max_value as f64;
+        let pixels = samples
+            .chunks(channels)
+            .map(|c| match c {
+                [r, g, b] => Color::new(r * scale, g * scale, b * scale),
+                _ => Color::new(c[0] * scale, c[0] * scale, c[0] * scale),
+            })
+            .collect();
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+            region: pnm_region(data),
+        })
+    }
+
+    fn decode_pfm(data: &[u8]) -> io::Result<Image> {
+        let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+
+        let mut pos = 0;
+        let channels = match next_token(data, &mut pos) {
+            Some(b"PF") => 3,
+            Some(b"Pf") => 1,
+            _ => return Err(invalid("not a PFM file")),
+        };
+        let mut header = [0.0f64; 3];
+        for value in header.iter_mut() {
+            *value = next_token(data, &mut pos)
+                .and_then(|t| std::str::from_utf8(t).ok())
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid("malformed PFM header"))?;
+        }
+        let [width, height, scale] = header;
+        if width < 0.0 || height < 0.0 || width.fract() != 0.0 || height.fract() != 0.0 {
+            return Err(invalid("malformed PFM header"));
+        }
+        let (width, height) = (width as usize, height as usize);
+
+        // Exactly one whitespace byte separates the header from the data,
+        // which is little-endian if the scale is negative.
+        pos += 1;
+        let raw = width
+            .checked_mul(height)
+            .and_then(|n| n.checked_mul(channels * 4))
+            .and_then(|n| n.checked_add(pos))
+            .and_then(|end| data.get(pos..end))
+            .ok_or_else(|| invalid("truncated PFM pixel data"))?;
+        let samples: Vec<f64> = raw
+            .chunks(4)
+            .map(|c| {
+                let bytes = [c[0], c[1], c[2], c[3]];
+                if scale < 0.0 {
+                    f32::from_le_bytes(bytes) as f64
+                } else {
+                    f32::from_be_bytes(bytes) as f64
+                }
+            })
+            .collect();
+
+        // Rows run from the bottom of the image to the top.
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in samples.chunks((width * channels).max(1)).rev() {
+            pixels.extend(row.chunks(channels).map(|c| match c {
+                [r, g, b] => Color::new(*r, *g, *b),
+                _ => Color::new(c[0], c[0], c[0]),
+            }));
+        }
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+            region: pnm_region(data),
+        })
+    }
+}
+
+// The `# region` comment among the header lines of a PNM or PFM file.
+fn pnm_region(data: &[u8]) -> Option<Region> {
+    let line = data
+        .split(|&b| b == b'\n')
+        .take_while(|line| line.starts_with(b"P") || line.starts_with(b"#"))
+        .find_map(|line| line.strip_prefix(b"# region "))?;
+    let values: Vec<usize> = std::str::from_utf8(line)
+        .ok()?
+        .split_whitespace()
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+
+    match values[..] {
+        [x, y, full_width, full_height] => Some(Region {
+            x,
+            y,
+            full_width,
+            full_height,
+        }),
+        _ => None,
+    }
+}
+
+fn next_token<'a>(data: &'a [u8], pos: &mut usize) -> Option<&'a [u8]> {
+    loop {
+        while *pos < data.len() && data[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < data.len() && data[*pos] == b'#' {
+            while *pos < data.len() && data[*pos] != b'\n' {
+                *pos += 1;
+            }
+            continue;
+        }
+        break;
+    }
+
+    let start = *pos;
+    while *pos < data.len() && !data[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+
+    if start == *pos {
+        None
+    } else {
+        Some(&data[start..*pos])
+    }
+}
+
+fn write_png_chunk(output: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    output.write_all(&(data.len() as u32).to_be_bytes())?;
+    output.write_all(kind)?;
+    output.write_all(data)?;
+
+    let mut crc = crc32(0xffff_ffff, kind);
+    crc = crc32(crc, data);
+    output.write_all(&(!crc).to_be_bytes())
+}
+
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -20,11 +20,18 @@ impl Interval {
         Interval { min, max }
     }
 
-    pub fn _size(&self) -> f64 {
+    pub fn enclosing(a: Interval, b: Interval) -> Self {
+        Interval {
+            min: f64::min(a.min, b.min),
+            max: f64::max(a.max, b.max),
+        }
+    }
+
+    pub fn size(&self) -> f64 {
         self.max - self.min
     }
 
-    pub fn _contains(&self, x: f64) -> bool {
+    pub fn contains(&self, x: f64) -> bool {
         self.min <= x && x <= self.max
     }
 
@@ -32,6 +39,11 @@ impl Interval {
         self.min < x && x < self.max
     }
 
+    pub fn expand(&self, delta: f64) -> Interval {
+        let padding = delta / 2.0;
+        Interval::new(self.min - padding, self.max + padding)
+    }
+
     pub fn clamp(&self, x: f64) -> f64 {
         if x < self.min {
             return self.min;
@@ -43,12 +55,12 @@ impl Interval {
     }
 }
 
-pub const _EMPTY: Interval = Interval {
+pub const EMPTY: Interval = Interval {
     min: f64::INFINITY,
     max: f64::NEG_INFINITY,
 };
 
-pub const _UNIVERSE: Interval = Interval {
+pub const UNIVERSE: Interval = Interval {
     min: f64::NEG_INFINITY,
     max: f64::INFINITY,
 };
@@ -0,0 +1,283 @@
+use std::fmt;
+
+// A small JSON reader for scene files. Every value remembers where it
+// started so the scene loader can point at the offending line and column.
+
+#[derive(Debug, Clone)]
+pub struct Value {
+    pub kind: ValueKind,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum ValueKind {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            ValueKind::Null => "null",
+            ValueKind::Bool(_) => "a boolean",
+            ValueKind::Number(_) => "a number",
+            ValueKind::String(_) => "a string",
+            ValueKind::Array(_) => "an array",
+            ValueKind::Object(_) => "an object",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+pub fn parse(source: &str) -> Result<Value, ParseError> {
+    let mut parser = Parser {
+        chars: source.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.peek().is_some() {
+        return Err(parser.error("unexpected trailing characters"));
+    }
+
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        if self.peek() == Some(expected) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{expected}`")))
+        }
+    }
+
+    // Whitespace plus `//` line comments, which are handy in hand-written
+    // scenes even though plain JSON does not allow them.
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() {
+                self.bump();
+            } else if c == '/' && self.chars.get(self.pos + 1) == Some(&'/') {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        let (line, column) = (self.line, self.column);
+
+        let kind = match self.peek() {
+            None => return Err(self.error("unexpected end of input")),
+            Some('{') => self.object()?,
+            Some('[') => self.array()?,
+            Some('"') => ValueKind::String(self.string()?),
+            Some('-' | '0'..='9') => self.number()?,
+            Some(c) if c.is_alphabetic() => self.keyword()?,
+            Some(c) => return Err(self.error(&format!("unexpected character `{c}`"))),
+        };
+
+        Ok(Value { kind, line, column })
+    }
+
+    fn object(&mut self) -> Result<ValueKind, ParseError> {
+        self.expect('{')?;
+        let mut members: Vec<(String, Value)> = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(ValueKind::Object(members));
+        }
+
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a string key"));
+            }
+            let key_error = self.error("");
+            let key = self.string()?;
+            if members.iter().any(|(k, _)| *k == key) {
+                return Err(ParseError {
+                    message: format!("duplicate key `{key}`"),
+                    ..key_error
+                });
+            }
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push((key, value));
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some('}') => {
+                    self.bump();
+                    return Ok(ValueKind::Object(members));
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<ValueKind, ParseError> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.bump();
+            return Ok(ValueKind::Array(items));
+        }
+
+        loop {
+            items.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                }
+                Some(']') => {
+                    self.bump();
+                    return Ok(ValueKind::Array(items));
+                }
+                _ => return Err(self.error("expected `,` or `]`")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.expect('"')?;
+        let mut out = String::new();
+
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(out),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = 0;
+                            for _ in 0..4 {
+                                let digit = self
+                                    .bump()
+                                    .and_then(|c| c.to_digit(16))
+                                    .ok_or_else(|| self.error("invalid unicode escape"))?;
+                                code = code * 16 + digit;
+                            }
+                            char::from_u32(code)
+                                .ok_or_else(|| self.error("invalid unicode escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape sequence")),
+                    };
+                    out.push(escaped);
+                }
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<ValueKind, ParseError> {
+        let start_error = self.error("invalid number");
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            self.bump();
+        }
+
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map(ValueKind::Number)
+            .map_err(|_| start_error)
+    }
+
+    fn keyword(&mut self) -> Result<ValueKind, ParseError> {
+        let start_error = self.error("");
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_alphanumeric()) {
+            self.bump();
+        }
+
+        let word: String = self.chars[start..self.pos].iter().collect();
+        match word.as_str() {
+            "true" => Ok(ValueKind::Bool(true)),
+            "false" => Ok(ValueKind::Bool(false)),
+            "null" => Ok(ValueKind::Null),
+            _ => Err(ParseError {
+                message: format!("unexpected identifier `{word}`"),
+                ..start_error
+            }),
+        }
+    }
+}
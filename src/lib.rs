@@ -0,0 +1,59 @@
+// The renderer as a library. The binary in `main.rs` is one consumer of it;
+// other tools can build scenes in code or load scene files and render them
+// the same way.
+
+pub mod aabb;
+pub mod aov;
+pub mod bvh;
+pub mod camera;
+pub mod checkpoint;
+pub mod color;
+pub mod constant_medium;
+pub mod csg;
+pub mod cuboid;
+pub mod disk;
+pub mod exr;
+pub mod film;
+pub mod heightfield;
+pub mod hittable;
+pub mod image;
+pub mod interval;
+mod json;
+pub mod material;
+mod onb;
+mod perlin;
+pub mod plane;
+mod polynomial;
+pub mod presets;
+pub mod quad;
+pub mod quadric;
+pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod sdf;
+pub mod sphere;
+pub mod stats;
+pub mod texture;
+pub mod torus;
+pub mod transform;
+pub mod utils;
+pub mod vec3;
+
+pub use aov::{Aov, AovBuffers, AovPixel};
+pub use camera::{
+    AdaptiveSampling, Aperture, Background, Camera, CameraBuilder, CameraError, CropWindow,
+    FisheyeMapping, PhysicalCamera, ProgressCallback, Projection, RenderOptions, RenderProgress,
+};
+pub use checkpoint::Checkpoint;
+pub use color::Color;
+pub use film::{Film, Filter, PixelStats};
+pub use hittable::{HitRecord, Hittable, HittableList};
+pub use image::{Image, ImageFormat, Region};
+pub use interval::Interval;
+pub use material::Material;
+pub use ray::Ray;
+pub use sampler::{Sampler, SamplerKind};
+pub use scene::{Scene, SceneError};
+pub use stats::{Primitive, RenderStats};
+pub use texture::Texture;
+pub use vec3::Vec3;
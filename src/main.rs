@@ -1,100 +1,450 @@
-mod camera;
-mod color;
-mod hittable;
-mod interval;
-mod material;
-mod ray;
-mod sphere;
-mod utils;
-mod vec3;
-
-use camera::Camera;
-use color::Color;
-use hittable::HittableList;
-use material::{Dielectric, Lambertian, Metal};
-use sphere::Sphere;
+mod cli;
+mod distributed;
+mod progress;
+
+use cli::Command;
+use progress::ProgressBar;
+use raytracing::{
+    presets, scene, utils, AdaptiveSampling, Aov, AovBuffers, Camera, Checkpoint, Film,
+    HittableList, Image, ImageFormat, RenderOptions, RenderStats, Scene, SceneError,
+};
 use std::time::Instant;
-use std::{fs::File, rc::Rc};
-use utils::{random_double, random_double_range};
-use vec3::Vec3;
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+};
+
+fn main() -> ExitCode {
+    let options = match cli::parse(env::args().skip(1)) {
+        Ok(Command::Render(options)) => options,
+        Ok(Command::Worker(options)) => {
+            return match distributed::work(&options) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Ok(Command::Merge(options)) => {
+            return match merge(&options) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("error: {err}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Ok(Command::Help) => {
+            print!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(err) => {
+            eprintln!("error: {err}\n\nFor more information, try `--help`.");
+            return ExitCode::from(2);
+        }
+    };
+
+    if !presets::NAMES.contains(&options.scene.as_str()) && !Path::new(&options.scene).exists() {
+        eprintln!(
+            "error: `{}` is neither a scene file nor a built-in scene ({})",
+            options.scene,
+            presets::NAMES.join(", ")
+        );
+        return ExitCode::from(2);
+    }
+
+    match run(&options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(options: &cli::Options) -> Result<(), String> {
+    // The scene file is read once, so the render, the checkpoint fingerprint
+    // and the workers all see the same contents; built-in scenes go by name.
+    let source = if presets::NAMES.contains(&options.scene.as_str()) {
+        String::new()
+    } else {
+        fs::read_to_string(&options.scene).map_err(|err| format!("{}: {err}", options.scene))?
+    };
+    let scene = prepare_scene(options, Some(&source))?;
+    let fingerprint = Checkpoint::fingerprint(&scene.camera, &source);
+    let camera = scene
+        .camera
+        .build()
+        .map_err(|err| format!("camera: {err}"))?;
+
+    let mut render_options = RenderOptions {
+        seed: options.seed,
+        ..Default::default()
+    };
+    if let Some(threads) = options.threads {
+        render_options.threads = threads;
+    }
 
-fn main() -> std::io::Result<()> {
     let current = Instant::now();
     println!("Started rendering image");
 
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width = 800;
-    let samples_per_pixel = 400;
-    let max_depth = 20;
-    let vfov = 20.0;
-    let look_from = Vec3::new(13.0, 2.0, 3.0);
-    let look_at = Vec3::new(0.0, 0.0, 0.0);
-    let v_up = Vec3::new(0.0, 1.0, 0.0);
-    let defocus_angle = 0.6;
-    let focus_dist = 10.0;
-
-    let camera = Camera::new(
-        aspect_ratio,
-        image_width,
-        samples_per_pixel,
-        max_depth,
-        vfov,
-        look_from,
-        look_at,
-        v_up,
-        defocus_angle,
-        focus_dist,
+    if let Some(address) = &options.serve {
+        let bar = ProgressBar::new("tiles");
+        let args: Vec<String> = env::args().skip(1).collect();
+        // The workers get the scene file itself, since they may not share
+        // the coordinator's filesystem.
+        let image = distributed::serve(address, &args, &source, &camera, options.tile_size, &bar)?;
+        bar.clear();
+        image
+            .save(&options.output, options.format)
+            .map_err(|err| format!("{}: {err}", options.output.display()))?;
+        println!("Elapsed time : {:.4?}", current.elapsed());
+        print_summary(&bar);
+        return Ok(());
+    }
+
+    let bar = ProgressBar::new("rows");
+    render_options.progress = Some(bar.callback());
+    let stats = Arc::new(Mutex::new(RenderStats::default()));
+    if options.stats.is_some() {
+        render_options.stats = Some(Arc::clone(&stats));
+    }
+
+    let in_passes = options.snapshot_interval.is_some() || options.checkpoint.is_some();
+    let pass_samples = match (options.pass_samples, in_passes) {
+        (Some(n), _) => Some(n as usize),
+        (None, true) => Some(1),
+        (None, false) => None,
+    };
+    let film = match pass_samples {
+        Some(pass_samples) => render_progressive(
+            &camera,
+            &scene.world,
+            &render_options,
+            options,
+            pass_samples,
+            fingerprint,
+            &bar,
+        )?,
+        None => camera.render_film(&scene.world, &render_options),
+    };
+    bar.clear();
+    save_image(&camera, &film, options)?;
+    if let Some(path) = &options.heatmap {
+        let format = ImageFormat::from_path(path).unwrap_or(ImageFormat::Ppm);
+        let mut heatmap = camera.sample_heatmap(&film);
+        if options.full_frame {
+            heatmap = heatmap.full_frame();
+        }
+        heatmap
+            .save(path, format)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+    if let Some(path) = &options.aovs {
+        let aovs = camera.render_aovs(&scene.world, &render_options);
+        save_aovs(&aovs, &camera.develop(&film), path, options)?;
+    }
+    if let Some(path) = &options.stats {
+        let json = stats.lock().expect("render stats poisoned").to_json();
+        fs::write(path, json).map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+
+    let elapsed = current.elapsed();
+    println!("Elapsed time : {:.4?}", elapsed);
+    print_summary(&bar);
+
+    Ok(())
+}
+
+fn print_summary(bar: &ProgressBar) {
+    let summary = bar.summary();
+    println!(
+        "Rays traced  : {} on {} paths, {:.2} per path, {}",
+        summary.rays,
+        summary.paths,
+        summary.average_depth(),
+        progress::format_rate(summary.rays_per_second())
     );
+}
+
+// Loads the scene and applies the command-line overrides to its camera.
+// `source`, if given, is the contents of the scene file, which is then not
+// read from disk.
+fn prepare_scene(options: &cli::Options, source: Option<&str>) -> Result<Scene, String> {
+    utils::seed(options.seed);
+    let scene = load_scene(&options.scene, source)?;
+    let mut builder = scene.camera;
+    if let Some(width) = options.width {
+        builder = builder.image_width(width);
+    }
+    if let Some(spp) = options.samples_per_pixel {
+        builder = builder.samples_per_pixel(spp);
+    }
+    if let Some(depth) = options.max_depth {
+        builder = builder.max_depth(depth);
+    }
+    if let Some(sampler) = options.sampler {
+        builder = builder.sampler(sampler);
+    }
+    if let Some(crop) = options.crop {
+        builder = builder.crop(crop);
+    }
+    if options.noise_threshold.is_some() || options.min_samples.is_some() {
+        let defaults = AdaptiveSampling::default();
+        builder = builder.adaptive(AdaptiveSampling {
+            min_samples: options.min_samples.unwrap_or(defaults.min_samples),
+            noise_threshold: options.noise_threshold.unwrap_or(defaults.noise_threshold),
+        });
+    }
+    Ok(Scene {
+        camera: builder,
+        world: scene.world,
+    })
+}
 
-    let mat_ground = Rc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
-
-    let mut world = HittableList::new();
-    world.add(Rc::new(Sphere::new(
-        Vec3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        mat_ground,
-    )));
-
-    for i in -11..11 {
-        for j in -11..11 {
-            let mat_choice = random_double();
-            let center = Vec3::new(
-                i as f64 + 0.9 * random_double(),
-                0.2,
-                j as f64 + 0.9 * random_double(),
+// Renders in passes of `pass_samples` samples per pixel. With `--pass-spp`
+// or `--snapshot-interval` the output is rewritten after each pass, or after
+// the first pass to end `--snapshot-interval` seconds after the last write;
+// with `--checkpoint` the render state is saved on the same schedule every
+// `--checkpoint-interval` seconds, and `--resume` picks it up again.
+fn render_progressive(
+    camera: &Camera,
+    world: &HittableList,
+    render_options: &RenderOptions,
+    options: &cli::Options,
+    pass_samples: usize,
+    fingerprint: u64,
+    bar: &ProgressBar,
+) -> Result<Film, String> {
+    let total = camera.samples_per_pixel();
+    let snapshots = options.pass_samples.is_some() || options.snapshot_interval.is_some();
+    let (mut film, mut done) = match &options.checkpoint {
+        Some(path) if options.resume => {
+            let (width, height) = camera.image_size();
+            let checkpoint = Checkpoint::load(path, width, height)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            check_checkpoint(&checkpoint, camera, options, pass_samples, fingerprint)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            bar.clear();
+            println!(
+                "Resuming from {}/{total} samples per pixel",
+                checkpoint.samples_done
             );
+            (checkpoint.film, checkpoint.samples_done)
+        }
+        _ => (camera.new_film(), 0),
+    };
+    let mut last_write = Instant::now();
+    let mut last_checkpoint = Instant::now();
 
-            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
-                if mat_choice < 0.8 {
-                    let albedo = Color::random_vec().elementwise_mul(Color::random_vec());
-                    let sphere_mat = Rc::new(Lambertian::new(albedo));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_mat)));
-                } else if mat_choice < 0.95 {
-                    let albedo = Color::random_with_min_max(0.5, 1.0);
-                    let fuzz = random_double_range(0.0, 0.5);
-                    let sphere_mat = Rc::new(Metal::new(albedo, fuzz));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_mat)));
-                } else {
-                    let sphere_mat = Rc::new(Dielectric::new(1.50));
-                    world.add(Rc::new(Sphere::new(center, 0.2, sphere_mat)));
-                }
+    let passes = (total - done).div_ceil(pass_samples);
+    let mut pass = 0;
+    while done < total {
+        let end = usize::min(done + pass_samples, total);
+        bar.start_pass(pass, passes);
+        camera.render_pass(world, render_options, &mut film, done..end);
+        done = end;
+        pass += 1;
+        if done == total {
+            break;
+        }
+
+        let due = options
+            .snapshot_interval
+            .is_none_or(|interval| last_write.elapsed().as_secs_f64() >= interval);
+        if snapshots && due {
+            save_image(camera, &film, options)?;
+            last_write = Instant::now();
+            bar.clear();
+            println!("Wrote {done}/{total} samples per pixel");
+        }
+
+        if let Some(path) = &options.checkpoint {
+            if last_checkpoint.elapsed().as_secs_f64() >= options.checkpoint_interval {
+                let checkpoint = Checkpoint {
+                    scene: options.scene.clone(),
+                    seed: options.seed,
+                    samples_per_pixel: total,
+                    pass_samples,
+                    fingerprint,
+                    samples_done: done,
+                    film,
+                };
+                checkpoint
+                    .save(path)
+                    .map_err(|err| format!("{}: {err}", path.display()))?;
+                film = checkpoint.film;
+                last_checkpoint = Instant::now();
+                bar.clear();
+                println!("Saved checkpoint at {done}/{total} samples per pixel");
+            }
+        }
+    }
+
+    // The finished image makes the checkpoint obsolete.
+    if let Some(path) = &options.checkpoint {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(format!("{}: {err}", path.display()));
             }
+            _ => {}
         }
     }
 
-    let mat1 = Rc::new(Dielectric::new(1.50));
-    world.add(Rc::new(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1)));
+    Ok(film)
+}
 
-    let mat2 = Rc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1)));
-    world.add(Rc::new(Sphere::new(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat2)));
+// A checkpoint can only be resumed by the render that wrote it.
+fn check_checkpoint(
+    checkpoint: &Checkpoint,
+    camera: &Camera,
+    options: &cli::Options,
+    pass_samples: usize,
+    fingerprint: u64,
+) -> Result<(), String> {
+    let mismatch = if checkpoint.scene != options.scene {
+        Some(format!("scene `{}`", checkpoint.scene))
+    } else if checkpoint.seed != options.seed {
+        Some(format!("seed {}", checkpoint.seed))
+    } else if checkpoint.samples_per_pixel != camera.samples_per_pixel() {
+        Some(format!(
+            "{} samples per pixel",
+            checkpoint.samples_per_pixel
+        ))
+    } else if checkpoint.pass_samples != pass_samples {
+        Some(format!("passes of {} samples", checkpoint.pass_samples))
+    } else if checkpoint.fingerprint != fingerprint {
+        Some("a different camera or scene file".to_string())
+    } else {
+        None
+    };
 
-    let mat3 = Rc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
-    world.add(Rc::new(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat3)));
+    match mismatch {
+        Some(what) => Err(format!(
+            "the checkpoint is for a render with {what}; resume with the options it was started with"
+        )),
+        None => Ok(()),
+    }
+}
 
-    let mut file = File::create("output.ppm")?;
-    camera.render(&world, &mut file)?;
-    let elapsed = current.elapsed();
-    println!("Elapsed time : {:.4?}", elapsed);
+fn save_image(camera: &Camera, film: &Film, options: &cli::Options) -> Result<(), String> {
+    let mut image = camera.develop(film);
+    if options.full_frame {
+        image = image.full_frame();
+    }
+    image
+        .save(&options.output, options.format)
+        .map_err(|err| format!("{}: {err}", options.output.display()))
+}
 
+// Writes the AOVs into one EXR file along with the image, or into an image
+// each: raw values for PFM, previews for the 8-bit formats.
+fn save_aovs(
+    aovs: &AovBuffers,
+    beauty: &Image,
+    path: &Path,
+    options: &cli::Options,
+) -> Result<(), String> {
+    let format = ImageFormat::from_path(path).unwrap_or(ImageFormat::Ppm);
+    if format == ImageFormat::Exr {
+        let mut file = fs::File::create(path)
+            .map(io::BufWriter::new)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        return aovs
+            .write_exr(&mut file, Some(beauty))
+            .and_then(|()| file.flush())
+            .map_err(|err| format!("{}: {err}", path.display()));
+    }
+
+    for aov in Aov::ALL {
+        let mut image = match format {
+            ImageFormat::Pfm => aovs.image(aov),
+            _ => aovs.preview(aov),
+        };
+        if options.full_frame {
+            image = image.full_frame();
+        }
+        let mut name = path.file_stem().unwrap_or_default().to_owned();
+        name.push(format!(".{aov}.{format}"));
+        let aov_path = path.with_file_name(name);
+        image
+            .save(&aov_path, format)
+            .map_err(|err| format!("{}: {err}", aov_path.display()))?;
+    }
     Ok(())
 }
+
+// Pieces crops saved as PPM or PFM files back into the frame they were cut
+// from.
+fn merge(options: &cli::MergeOptions) -> Result<(), String> {
+    let mut frame: Option<Image> = None;
+    for path in &options.crops {
+        let format = ImageFormat::from_path(path);
+        if !matches!(format, Some(ImageFormat::Ppm | ImageFormat::Pfm)) {
+            return Err(format!(
+                "{}: only crops saved as PPM or PFM can be merged",
+                path.display()
+            ));
+        }
+        let crop = Image::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let region = crop
+            .region()
+            .ok_or_else(|| format!("{}: not a cropped render", path.display()))?;
+        let frame = frame.get_or_insert_with(|| Image::new(region.full_width, region.full_height));
+        if (frame.width(), frame.height()) != (region.full_width, region.full_height) {
+            return Err(format!(
+                "{}: cut from a {}x{} image, but the others are {}x{}",
+                path.display(),
+                region.full_width,
+                region.full_height,
+                frame.width(),
+                frame.height()
+            ));
+        }
+
+        // PPM files hold gamma-encoded values; undo the gamma that saving
+        // the merged image applies again. PFM files are linear already.
+        if format == Some(ImageFormat::Pfm) {
+            frame.paste(&crop);
+            continue;
+        }
+        let mut linear = crop.clone();
+        for j in 0..crop.height() {
+            for i in 0..crop.width() {
+                let c = crop.pixel(i, j);
+                linear.set_pixel(i, j, c.elementwise_mul(c));
+            }
+        }
+        frame.paste(&linear);
+    }
+
+    let frame = frame.expect("the command line names at least one crop");
+    frame
+        .save(&options.output, options.format)
+        .map_err(|err| format!("{}: {err}", options.output.display()))
+}
+
+fn load_scene(name: &str, source: Option<&str>) -> Result<Scene, String> {
+    if let Some(scene) = presets::by_name(name) {
+        // The scene has loaded the map already; this only reads the result.
+        if presets::EARTH_MAP_SCENES.contains(&name) {
+            if let Err(err) = presets::earth_map() {
+                eprintln!("warning: {err}; the globe will render cyan");
+            }
+        }
+        return Ok(scene);
+    }
+
+    let scene = match source {
+        Some(source) => scene::parse(source, Path::new(name).parent().unwrap_or(Path::new("."))),
+        None => scene::load(name),
+    };
+    scene.map_err(|err| match err {
+        SceneError::Invalid { .. } => format!("{name}:{err}"),
+        SceneError::Io(_) => format!("{name}: {err}"),
+    })
+}
@@ -1,7 +1,14 @@
-use crate::{color::Color, hittable::HitRecord, ray::Ray, utils, vec3::Vec3};
-use std::ops::Neg;
+use crate::{
+    color::Color,
+    hittable::HitRecord,
+    ray::Ray,
+    sampler::{sample_unit_vector, Sampler},
+    texture::{SolidColor, Texture},
+    vec3::Vec3,
+};
+use std::{ops::Neg, sync::Arc};
 
-pub trait Material {
+pub trait Material: Send + Sync {
     #[allow(dead_code, unused_variables)]
     fn scatter(
         &self,
@@ -9,42 +16,106 @@ pub trait Material {
         rec: &mut HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         false
     }
+
+    #[allow(unused_variables)]
+    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // The surface color at a hit, without lighting, for the albedo AOV.
+    #[allow(unused_variables)]
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    // The material ID for the material ID AOV; zero for none.
+    fn id(&self) -> u32 {
+        0
+    }
 }
 
 pub struct MaterialZST;
 
 impl Material for MaterialZST {}
 
+// Gives a material an ID and otherwise behaves exactly like it.
+pub struct Labeled {
+    material: Arc<dyn Material>,
+    id: u32,
+}
+
+impl Labeled {
+    pub fn new(material: Arc<dyn Material>, id: u32) -> Self {
+        Labeled { material, id }
+    }
+}
+
+impl Material for Labeled {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &mut HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        self.material
+            .scatter(r_in, rec, attenuation, scattered, sampler)
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Color {
+        self.material.emitted(u, v, p)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.material.albedo(rec)
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+}
+
 pub struct Lambertian {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
-        Lambertian { albedo }
+        Lambertian::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Lambertian { tex }
     }
 }
 
 impl Material for Lambertian {
     fn scatter(
         &self,
-        _r_in: &Ray,
+        r_in: &Ray,
         rec: &mut HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
-        let mut scatter_dir = rec.normal + Vec3::random_unit_vector();
+        let mut scatter_dir = rec.normal + sample_unit_vector(sampler.get_2d());
         if scatter_dir.near_zero() {
             scatter_dir = rec.normal;
         }
 
-        *scattered = Ray::new(rec.p, scatter_dir);
-        *attenuation = self.albedo;
+        *scattered = Ray::new(rec.p, scatter_dir, r_in.time);
+        *attenuation = self.tex.value(rec.u, rec.v, rec.p);
         true
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, rec.p)
+    }
 }
 
 pub struct Metal {
@@ -68,13 +139,19 @@ impl Material for Metal {
         rec: &mut HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         let mut reflected = Vec3::reflect(&r_in.dir, &rec.normal);
-        reflected = Vec3::unit_vector(reflected) + (self.fuzz * Vec3::random_unit_vector());
-        *scattered = Ray::new(rec.p, reflected);
+        reflected =
+            Vec3::unit_vector(reflected) + (self.fuzz * sample_unit_vector(sampler.get_2d()));
+        *scattered = Ray::new(rec.p, reflected, r_in.time);
         *attenuation = self.albedo;
         scattered.dir.dot(rec.normal) > 0.0
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
 pub struct Dielectric {
@@ -100,6 +177,7 @@ impl Material for Dielectric {
         rec: &mut HitRecord,
         attenuation: &mut Color,
         scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
     ) -> bool {
         *attenuation = Color::new(1.0, 1.0, 1.0);
         let ri = if rec.front_face {
@@ -112,15 +190,80 @@ impl Material for Dielectric {
         let cos_theta = f64::min(rec.normal.dot(unit_dir.neg()), 1.0);
         let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
         let cannot_refract = ri * sin_theta > 1.0;
-        let direction =
-            if cannot_refract || Self::reflectance(cos_theta, ri) > utils::random_double() {
-                Vec3::reflect(&unit_dir, &rec.normal)
-            } else {
-                Vec3::refract(&unit_dir, &rec.normal, ri)
-            };
+        let direction = if cannot_refract || Self::reflectance(cos_theta, ri) > sampler.get_1d() {
+            Vec3::reflect(&unit_dir, &rec.normal)
+        } else {
+            Vec3::refract(&unit_dir, &rec.normal, ri)
+        };
+
+        *scattered = Ray::new(rec.p, direction, r_in.time);
+
+        true
+    }
+
+    // Glass lets all light through, so denoisers expect white.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        DiffuseLight::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        DiffuseLight { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, u: f64, v: f64, p: Vec3) -> Color {
+        self.tex.value(u, v, p)
+    }
+
+    // The emitted color, clamped to the range of a reflectance.
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let c = self.tex.value(rec.u, rec.v, rec.p);
+        Color::new(c.x.min(1.0), c.y.min(1.0), c.z.min(1.0))
+    }
+}
 
-        *scattered = Ray::new(rec.p, direction);
+// Scatters uniformly in all directions; the phase function of a
+// `ConstantMedium`.
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Isotropic::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Isotropic { tex }
+    }
+}
 
+impl Material for Isotropic {
+    fn scatter(
+        &self,
+        r_in: &Ray,
+        rec: &mut HitRecord,
+        attenuation: &mut Color,
+        scattered: &mut Ray,
+        sampler: &mut dyn Sampler,
+    ) -> bool {
+        *scattered = Ray::new(rec.p, sample_unit_vector(sampler.get_2d()), r_in.time);
+        *attenuation = self.tex.value(rec.u, rec.v, rec.p);
         true
     }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.tex.value(rec.u, rec.v, rec.p)
+    }
 }
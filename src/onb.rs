@@ -0,0 +1,34 @@
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = Vec3::unit_vector(n);
+        let a = if f64::abs(w.x) > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        // u x v = w, a right-handed basis.
+        let u = Vec3::unit_vector(a.cross(w));
+        let v = w.cross(u);
+
+        Onb { u, v, w }
+    }
+
+    #[inline]
+    pub fn to_local(self, a: Vec3) -> Vec3 {
+        Vec3::new(a.dot(self.u), a.dot(self.v), a.dot(self.w))
+    }
+
+    #[inline]
+    pub fn to_world(self, a: Vec3) -> Vec3 {
+        a.x * self.u + a.y * self.v + a.z * self.w
+    }
+}
@@ -0,0 +1,101 @@
+use crate::{utils::random_double, vec3::Vec3};
+
+const POINT_COUNT: usize = 256;
+
+pub struct Perlin {
+    randvec: [Vec3; POINT_COUNT],
+    perm_x: [usize; POINT_COUNT],
+    perm_y: [usize; POINT_COUNT],
+    perm_z: [usize; POINT_COUNT],
+}
+
+impl Perlin {
+    pub fn new() -> Self {
+        let mut randvec = [Vec3::default(); POINT_COUNT];
+        for v in randvec.iter_mut() {
+            *v = Vec3::unit_vector(Vec3::random_with_min_max(-1.0, 1.0));
+        }
+
+        Perlin {
+            randvec,
+            perm_x: Perlin::generate_perm(),
+            perm_y: Perlin::generate_perm(),
+            perm_z: Perlin::generate_perm(),
+        }
+    }
+
+    pub fn noise(&self, p: Vec3) -> f64 {
+        let u = p.x - f64::floor(p.x);
+        let v = p.y - f64::floor(p.y);
+        let w = p.z - f64::floor(p.z);
+
+        let i = f64::floor(p.x) as i64;
+        let j = f64::floor(p.y) as i64;
+        let k = f64::floor(p.z) as i64;
+
+        let mut c = [[[Vec3::default(); 2]; 2]; 2];
+        for (di, plane) in c.iter_mut().enumerate() {
+            for (dj, row) in plane.iter_mut().enumerate() {
+                for (dk, corner) in row.iter_mut().enumerate() {
+                    *corner = self.randvec[self.perm_x[((i + di as i64) & 255) as usize]
+                        ^ self.perm_y[((j + dj as i64) & 255) as usize]
+                        ^ self.perm_z[((k + dk as i64) & 255) as usize]];
+                }
+            }
+        }
+
+        Perlin::perlin_interp(&c, u, v, w)
+    }
+
+    pub fn turb(&self, p: Vec3, depth: i32) -> f64 {
+        let mut accum = 0.0;
+        let mut temp_p = p;
+        let mut weight = 1.0;
+
+        for _ in 0..depth {
+            accum += weight * self.noise(temp_p);
+            weight *= 0.5;
+            temp_p *= 2.0;
+        }
+
+        f64::abs(accum)
+    }
+
+    fn generate_perm() -> [usize; POINT_COUNT] {
+        let mut p = [0; POINT_COUNT];
+        for (i, v) in p.iter_mut().enumerate() {
+            *v = i;
+        }
+
+        // Fisher-Yates shuffle.
+        for i in (1..POINT_COUNT).rev() {
+            let target = (random_double() * (i + 1) as f64) as usize;
+            p.swap(i, target.min(i));
+        }
+
+        p
+    }
+
+    fn perlin_interp(c: &[[[Vec3; 2]; 2]; 2], u: f64, v: f64, w: f64) -> f64 {
+        // Hermite smoothing hides the grid lines of plain trilinear blending.
+        let uu = u * u * (3.0 - 2.0 * u);
+        let vv = v * v * (3.0 - 2.0 * v);
+        let ww = w * w * (3.0 - 2.0 * w);
+
+        let mut accum = 0.0;
+        for (i, plane) in c.iter().enumerate() {
+            for (j, row) in plane.iter().enumerate() {
+                for (k, corner) in row.iter().enumerate() {
+                    let (fi, fj, fk) = (i as f64, j as f64, k as f64);
+                    let weight_v = Vec3::new(u - fi, v - fj, w - fk);
+                    accum += (fi * uu + (1.0 - fi) * (1.0 - uu))
+                        * (fj * vv + (1.0 - fj) * (1.0 - vv))
+                        * (fk * ww + (1.0 - fk) * (1.0 - ww))
+                        * corner.dot(weight_v);
+                }
+            }
+        }
+
+        accum
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::{self, Aabb},
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    onb::Onb,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+pub struct Plane {
+    point: Vec3,
+    basis: Onb,
+    mat: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3, mat: Arc<dyn Material>) -> Self {
+        Plane {
+            point,
+            basis: Onb::new(normal),
+            mat,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Plane);
+        let denom = self.basis.w.dot(r.dir);
+        if f64::abs(denom) < 1e-8 {
+            return false;
+        }
+
+        let t = (self.point - r.origin).dot(self.basis.w) / denom;
+        if !interval.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        // UVs are measured in world units along the plane's tangent axes, so
+        // textures tile instead of stretching over an infinite surface.
+        let local = self.basis.to_local(rec.p - self.point);
+        rec.u = local.x;
+        rec.v = local.y;
+        rec.set_face_normal(r, &self.basis.w);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        aabb::UNIVERSE
+    }
+}
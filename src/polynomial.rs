@@ -0,0 +1,144 @@
+use crate::interval::Interval;
+
+// Coefficients throughout this module are stored in ascending powers, so
+// `[c0, c1, c2]` is the polynomial c0 + c1 * x + c2 * x^2.
+
+pub fn eval(coeffs: &[f64], x: f64) -> f64 {
+    coeffs.iter().rev().fold(0.0, |acc, &c| acc * x + c)
+}
+
+pub fn derivative(coeffs: &[f64]) -> Vec<f64> {
+    coeffs
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, &c)| i as f64 * c)
+        .collect()
+}
+
+// Real roots of a * x^2 + b * x + c in ascending order. A vanishing leading
+// coefficient degrades to the linear case, which reports its root twice.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<(f64, f64)> {
+    if f64::abs(a) < 1e-12 {
+        if f64::abs(b) < 1e-12 {
+            return None;
+        }
+        let t = -c / b;
+        return Some((t, t));
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    // Numerically stable form that avoids cancellation between b and the
+    // square root of the discriminant.
+    let q = -0.5 * (b + f64::copysign(f64::sqrt(discriminant), b));
+    let (t0, t1) = if q == 0.0 { (0.0, 0.0) } else { (q / a, c / q) };
+
+    Some((f64::min(t0, t1), f64::max(t0, t1)))
+}
+
+// All real roots inside a finite interval, in ascending order.
+//
+// Roots are isolated recursively: the critical points of a polynomial (the
+// roots of its derivative) split the interval into monotonic pieces, each of
+// which holds at most one root. Every bracketed root is then refined with a
+// Newton iteration that falls back to bisection whenever a step would leave
+// the bracket, so convergence never depends on a good starting guess.
+pub fn find_roots(coeffs: &[f64], interval: Interval) -> Vec<f64> {
+    let coeffs = trim(coeffs);
+    match coeffs.len() {
+        0 | 1 => Vec::new(),
+        2 => {
+            let x = -coeffs[0] / coeffs[1];
+            if interval.contains(x) {
+                vec![x]
+            } else {
+                Vec::new()
+            }
+        }
+        3 => match solve_quadratic(coeffs[2], coeffs[1], coeffs[0]) {
+            Some((x0, x1)) => {
+                let mut roots: Vec<f64> = [x0, x1]
+                    .into_iter()
+                    .filter(|x| interval.contains(*x))
+                    .collect();
+                roots.dedup();
+                roots
+            }
+            None => Vec::new(),
+        },
+        _ => {
+            let mut bounds = vec![interval.min];
+            bounds.extend(find_roots(&derivative(coeffs), interval));
+            bounds.push(interval.max);
+
+            let mut roots: Vec<f64> = Vec::new();
+            for pair in bounds.windows(2) {
+                if let Some(x) = refine_root(coeffs, pair[0], pair[1]) {
+                    if roots.last().is_none_or(|&last| x - last > 1e-12) {
+                        roots.push(x);
+                    }
+                }
+            }
+            roots
+        }
+    }
+}
+
+fn trim(coeffs: &[f64]) -> &[f64] {
+    let scale = coeffs.iter().fold(0.0, |acc: f64, c| acc.max(c.abs()));
+    let mut len = coeffs.len();
+    while len > 0 && f64::abs(coeffs[len - 1]) <= scale * 1e-14 {
+        len -= 1;
+    }
+
+    &coeffs[..len]
+}
+
+fn refine_root(coeffs: &[f64], lo: f64, hi: f64) -> Option<f64> {
+    let f_lo = eval(coeffs, lo);
+    let f_hi = eval(coeffs, hi);
+    if f_lo == 0.0 {
+        return Some(lo);
+    }
+    if f_hi == 0.0 {
+        return Some(hi);
+    }
+    if f_lo.signum() == f_hi.signum() {
+        return None;
+    }
+
+    let d_coeffs = derivative(coeffs);
+    let (mut lo, mut hi) = if f_lo < 0.0 { (lo, hi) } else { (hi, lo) };
+    let mut x = 0.5 * (lo + hi);
+
+    for _ in 0..100 {
+        let f = eval(coeffs, x);
+        if f == 0.0 {
+            return Some(x);
+        }
+        if f < 0.0 {
+            lo = x;
+        } else {
+            hi = x;
+        }
+
+        let df = eval(&d_coeffs, x);
+        let newton = x - f / df;
+        let next = if df != 0.0 && newton > f64::min(lo, hi) && newton < f64::max(lo, hi) {
+            newton
+        } else {
+            0.5 * (lo + hi)
+        };
+
+        if f64::abs(next - x) <= 1e-12 * (1.0 + f64::abs(x)) {
+            return Some(next);
+        }
+        x = next;
+    }
+
+    Some(x)
+}
@@ -0,0 +1,562 @@
+use std::{
+    env,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use crate::{
+    bvh::BvhNode,
+    camera::{Background, CameraBuilder},
+    color::Color,
+    constant_medium::ConstantMedium,
+    cuboid::Cuboid,
+    hittable::{self, Hittable, HittableList},
+    image::Image,
+    material::{self, Dielectric, DiffuseLight, Lambertian, Material, Metal},
+    plane::Plane,
+    quad::Quad,
+    scene::Scene,
+    sphere::Sphere,
+    texture::{ImageTexture, NoiseTexture, Texture},
+    transform::Transform,
+    utils::{random_double, random_double_range},
+    vec3::Vec3,
+};
+
+// Scenes from the "Ray Tracing in One Weekend" series, rebuilt with the
+// book's parameters so they can serve as benchmarks and visual baselines.
+// Scenes that scatter objects at random draw from the global generator, so
+// they only come out the same for the same seed.
+pub const NAMES: &[&str] = &[
+    "random_spheres",
+    "two_perlin_spheres",
+    "earth",
+    "quads",
+    "simple_light",
+    "cornell_box",
+    "cornell_smoke",
+    "final_scene",
+];
+
+// The scenes that texture a globe with `earth_map`.
+pub const EARTH_MAP_SCENES: &[&str] = &["earth", "final_scene"];
+
+pub fn by_name(name: &str) -> Option<Scene> {
+    let scene = match name {
+        "random_spheres" => random_spheres(),
+        "two_perlin_spheres" => two_perlin_spheres(),
+        "earth" => earth(),
+        "quads" => quads(),
+        "simple_light" => simple_light(),
+        "cornell_box" => cornell_box(),
+        "cornell_smoke" => cornell_smoke(),
+        "final_scene" => final_scene(),
+        _ => return None,
+    };
+
+    Some(scene)
+}
+
+pub fn random_spheres() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(800)
+        .samples_per_pixel(400)
+        .max_depth(20)
+        .vfov(20.0)
+        .look_from(Vec3::new(13.0, 2.0, 3.0))
+        .look_at(Vec3::new(0.0, 0.0, 0.0))
+        .defocus_angle(0.6);
+
+    let mut labels = Labels::default();
+    let mat_ground = labels.material(Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))));
+
+    // The ground plane has an infinite bounding box, so it stays out of the
+    // BVH.
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Plane::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        mat_ground,
+    ))));
+
+    let mut spheres = HittableList::new();
+    for i in -11..11 {
+        for j in -11..11 {
+            let mat_choice = random_double();
+            let center = Vec3::new(
+                i as f64 + 0.9 * random_double(),
+                0.2,
+                j as f64 + 0.9 * random_double(),
+            );
+
+            if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
+                let sphere_mat: Arc<dyn Material> = if mat_choice < 0.8 {
+                    let albedo = Color::random_vec().elementwise_mul(Color::random_vec());
+                    Arc::new(Lambertian::new(albedo))
+                } else if mat_choice < 0.95 {
+                    let albedo = Color::random_with_min_max(0.5, 1.0);
+                    let fuzz = random_double_range(0.0, 0.5);
+                    Arc::new(Metal::new(albedo, fuzz))
+                } else {
+                    Arc::new(Dielectric::new(1.50))
+                };
+                let sphere_mat = labels.material(sphere_mat);
+                spheres.add(labels.object(Arc::new(Sphere::new(center, 0.2, sphere_mat))));
+            }
+        }
+    }
+
+    let mat1 = labels.material(Arc::new(Dielectric::new(1.50)));
+    spheres.add(labels.object(Arc::new(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, mat1))));
+
+    let mat2 = labels.material(Arc::new(Lambertian::new(Color::new(0.4, 0.2, 0.1))));
+    spheres.add(labels.object(Arc::new(Sphere::new(Vec3::new(-4.0, 1.0, 0.0), 1.0, mat2))));
+
+    let mat3 = labels.material(Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0)));
+    spheres.add(labels.object(Arc::new(Sphere::new(Vec3::new(4.0, 1.0, 0.0), 1.0, mat3))));
+
+    world.add(Arc::new(BvhNode::new(spheres)));
+
+    Scene { camera, world }
+}
+
+pub fn two_perlin_spheres() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3::new(13.0, 2.0, 3.0))
+        .look_at(Vec3::new(0.0, 0.0, 0.0))
+        .background(Background::Solid(Color::new(0.70, 0.80, 1.00)));
+
+    let mut labels = Labels::default();
+    let pertext: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+    let mat = labels.material(Arc::new(Lambertian::from_texture(pertext)));
+
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        mat.clone(),
+    ))));
+    world.add(labels.object(Arc::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 2.0, mat))));
+
+    Scene { camera, world }
+}
+
+pub fn earth() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3::new(0.0, 0.0, 12.0))
+        .look_at(Vec3::new(0.0, 0.0, 0.0))
+        .background(Background::Solid(Color::new(0.70, 0.80, 1.00)));
+
+    let mut labels = Labels::default();
+    let earth_surface = labels.material(Arc::new(Lambertian::from_texture(earth_texture())));
+
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        2.0,
+        earth_surface,
+    ))));
+
+    Scene { camera, world }
+}
+
+pub fn quads() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(1.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(80.0)
+        .look_from(Vec3::new(0.0, 0.0, 9.0))
+        .look_at(Vec3::new(0.0, 0.0, 0.0))
+        .background(Background::Solid(Color::new(0.70, 0.80, 1.00)));
+
+    let mut labels = Labels::default();
+    let left_red = labels.material(Arc::new(Lambertian::new(Color::new(1.0, 0.2, 0.2))));
+    let back_green = labels.material(Arc::new(Lambertian::new(Color::new(0.2, 1.0, 0.2))));
+    let right_blue = labels.material(Arc::new(Lambertian::new(Color::new(0.2, 0.2, 1.0))));
+    let upper_orange = labels.material(Arc::new(Lambertian::new(Color::new(1.0, 0.5, 0.0))));
+    let lower_teal = labels.material(Arc::new(Lambertian::new(Color::new(0.2, 0.8, 0.8))));
+
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(-3.0, -2.0, 5.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        left_red,
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(-2.0, -2.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        back_green,
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(3.0, -2.0, 1.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        Vec3::new(0.0, 4.0, 0.0),
+        right_blue,
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(-2.0, 3.0, 1.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 4.0),
+        upper_orange,
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(-2.0, -3.0, 5.0),
+        Vec3::new(4.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -4.0),
+        lower_teal,
+    ))));
+
+    Scene { camera, world }
+}
+
+pub fn simple_light() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .image_width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .vfov(20.0)
+        .look_from(Vec3::new(26.0, 3.0, 6.0))
+        .look_at(Vec3::new(0.0, 2.0, 0.0))
+        .background(Background::Solid(Color::new(0.0, 0.0, 0.0)));
+
+    let mut labels = Labels::default();
+    let pertext: Arc<dyn Texture> = Arc::new(NoiseTexture::new(4.0));
+    let mat = labels.material(Arc::new(Lambertian::from_texture(pertext)));
+    let difflight = labels.material(Arc::new(DiffuseLight::new(Color::new(4.0, 4.0, 4.0))));
+
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        mat.clone(),
+    ))));
+    world.add(labels.object(Arc::new(Sphere::new(Vec3::new(0.0, 2.0, 0.0), 2.0, mat))));
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(0.0, 7.0, 0.0),
+        2.0,
+        difflight.clone(),
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(3.0, 1.0, -2.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(0.0, 2.0, 0.0),
+        difflight,
+    ))));
+
+    Scene { camera, world }
+}
+
+pub fn cornell_box() -> Scene {
+    let mut labels = Labels::default();
+    let light = labels.material(Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0))));
+    let mut world = cornell_walls(
+        Quad::new(
+            Vec3::new(343.0, 554.0, 332.0),
+            Vec3::new(-130.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, -105.0),
+            light,
+        ),
+        &mut labels,
+    );
+
+    let white = labels.material(Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73))));
+    let (box1, box2) = cornell_blocks(white);
+    world.add(labels.object(box1));
+    world.add(labels.object(box2));
+
+    Scene {
+        camera: cornell_camera(),
+        world,
+    }
+}
+
+pub fn cornell_smoke() -> Scene {
+    let mut labels = Labels::default();
+    let light = labels.material(Arc::new(DiffuseLight::new(Color::new(7.0, 7.0, 7.0))));
+    let mut world = cornell_walls(
+        Quad::new(
+            Vec3::new(113.0, 554.0, 127.0),
+            Vec3::new(330.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 305.0),
+            light,
+        ),
+        &mut labels,
+    );
+
+    let white = labels.material(Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73))));
+    let (box1, box2) = cornell_blocks(white);
+    world.add(labels.object(Arc::new(ConstantMedium::new(
+        box1,
+        0.01,
+        Color::new(0.0, 0.0, 0.0),
+    ))));
+    world.add(labels.object(Arc::new(ConstantMedium::new(
+        box2,
+        0.01,
+        Color::new(1.0, 1.0, 1.0),
+    ))));
+
+    Scene {
+        camera: cornell_camera(),
+        world,
+    }
+}
+
+pub fn final_scene() -> Scene {
+    let camera = CameraBuilder::new()
+        .aspect_ratio(1.0)
+        .image_width(800)
+        .samples_per_pixel(10000)
+        .max_depth(40)
+        .vfov(40.0)
+        .look_from(Vec3::new(478.0, 278.0, -600.0))
+        .look_at(Vec3::new(278.0, 278.0, 0.0))
+        .background(Background::Solid(Color::new(0.0, 0.0, 0.0)));
+
+    let mut labels = Labels::default();
+    let ground = labels.material(Arc::new(Lambertian::new(Color::new(0.48, 0.83, 0.53))));
+    let mut boxes1 = HittableList::new();
+    let boxes_per_side = 20;
+    for i in 0..boxes_per_side {
+        for j in 0..boxes_per_side {
+            let w = 100.0;
+            let x0 = -1000.0 + i as f64 * w;
+            let z0 = -1000.0 + j as f64 * w;
+            let y1 = random_double_range(1.0, 101.0);
+            boxes1.add(labels.object(Arc::new(Cuboid::new(
+                Vec3::new(x0, 0.0, z0),
+                Vec3::new(x0 + w, y1, z0 + w),
+                ground.clone(),
+            ))));
+        }
+    }
+
+    let mut world = HittableList::new();
+    world.add(Arc::new(BvhNode::new(boxes1)));
+
+    let light = labels.material(Arc::new(DiffuseLight::new(Color::new(7.0, 7.0, 7.0))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(123.0, 554.0, 147.0),
+        Vec3::new(300.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 265.0),
+        light,
+    ))));
+
+    let center1 = Vec3::new(400.0, 400.0, 200.0);
+    let center2 = center1 + Vec3::new(30.0, 0.0, 0.0);
+    let sphere_material = labels.material(Arc::new(Lambertian::new(Color::new(0.7, 0.3, 0.1))));
+    world.add(labels.object(Arc::new(Sphere::moving(
+        center1,
+        center2,
+        50.0,
+        sphere_material,
+    ))));
+
+    let glass = labels.material(Arc::new(Dielectric::new(1.5)));
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(260.0, 150.0, 45.0),
+        50.0,
+        glass,
+    ))));
+    let metal = labels.material(Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 1.0)));
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(0.0, 150.0, 145.0),
+        50.0,
+        metal,
+    ))));
+
+    let boundary: Arc<dyn Hittable> = Arc::new(Sphere::new(
+        Vec3::new(360.0, 150.0, 145.0),
+        70.0,
+        labels.material(Arc::new(Dielectric::new(1.5))),
+    ));
+    world.add(labels.object(boundary.clone()));
+    world.add(labels.object(Arc::new(ConstantMedium::new(
+        boundary,
+        0.2,
+        Color::new(0.2, 0.4, 0.9),
+    ))));
+    let boundary: Arc<dyn Hittable> = Arc::new(Sphere::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        5000.0,
+        labels.material(Arc::new(Dielectric::new(1.5))),
+    ));
+    world.add(labels.object(Arc::new(ConstantMedium::new(
+        boundary,
+        0.0001,
+        Color::new(1.0, 1.0, 1.0),
+    ))));
+
+    let emat = labels.material(Arc::new(Lambertian::from_texture(earth_texture())));
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(400.0, 200.0, 400.0),
+        100.0,
+        emat,
+    ))));
+    let pertext: Arc<dyn Texture> = Arc::new(NoiseTexture::new(0.2));
+    let noise = labels.material(Arc::new(Lambertian::from_texture(pertext)));
+    world.add(labels.object(Arc::new(Sphere::new(
+        Vec3::new(220.0, 280.0, 300.0),
+        80.0,
+        noise,
+    ))));
+
+    let white = labels.material(Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73))));
+    let mut boxes2 = HittableList::new();
+    for _ in 0..1000 {
+        boxes2.add(labels.object(Arc::new(Sphere::new(
+            Color::random_with_min_max(0.0, 165.0),
+            10.0,
+            white.clone(),
+        ))));
+    }
+    world.add(Arc::new(
+        Transform::new(Arc::new(BvhNode::new(boxes2)))
+            .rotate_y(15.0)
+            .translate(Vec3::new(-100.0, 270.0, 395.0)),
+    ));
+
+    Scene { camera, world }
+}
+
+// Numbers the objects and materials of a built-in scene from 1 as they are
+// made, for the ID AOVs, the way scene files are numbered. Objects grouped
+// into a BVH are labeled one by one, before the grouping.
+#[derive(Default)]
+struct Labels {
+    objects: u32,
+    materials: u32,
+}
+
+impl Labels {
+    fn object(&mut self, object: Arc<dyn Hittable>) -> Arc<dyn Hittable> {
+        self.objects += 1;
+        Arc::new(hittable::Labeled::new(object, self.objects))
+    }
+
+    fn material(&mut self, material: Arc<dyn Material>) -> Arc<dyn Material> {
+        self.materials += 1;
+        Arc::new(material::Labeled::new(material, self.materials))
+    }
+}
+
+fn cornell_camera() -> CameraBuilder {
+    CameraBuilder::new()
+        .aspect_ratio(1.0)
+        .image_width(600)
+        .samples_per_pixel(200)
+        .max_depth(50)
+        .vfov(40.0)
+        .look_from(Vec3::new(278.0, 278.0, -800.0))
+        .look_at(Vec3::new(278.0, 278.0, 0.0))
+        .background(Background::Solid(Color::new(0.0, 0.0, 0.0)))
+}
+
+// The five walls of the box plus the given ceiling light.
+fn cornell_walls(light: Quad, labels: &mut Labels) -> HittableList {
+    let red = labels.material(Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05))));
+    let white = labels.material(Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73))));
+    let green = labels.material(Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15))));
+
+    let mut world = HittableList::new();
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        green,
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        red,
+    ))));
+    world.add(labels.object(Arc::new(light)));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        white.clone(),
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(-555.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -555.0),
+        white.clone(),
+    ))));
+    world.add(labels.object(Arc::new(Quad::new(
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        white,
+    ))));
+
+    world
+}
+
+// The tall and the short block, already rotated into place.
+fn cornell_blocks(mat: Arc<dyn Material>) -> (Arc<dyn Hittable>, Arc<dyn Hittable>) {
+    let box1 = Transform::new(Arc::new(Cuboid::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(165.0, 330.0, 165.0),
+        mat.clone(),
+    )))
+    .rotate_y(15.0)
+    .translate(Vec3::new(265.0, 0.0, 295.0));
+
+    let box2 = Transform::new(Arc::new(Cuboid::new(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(165.0, 165.0, 165.0),
+        mat,
+    )))
+    .rotate_y(-18.0)
+    .translate(Vec3::new(130.0, 0.0, 65.0));
+
+    (Arc::new(box1), Arc::new(box2))
+}
+
+// The book's earth map, looked up as `earthmap.ppm` in the directory named by
+// RTW_IMAGES and then in `images/`. The first file found is the one used, so
+// an unreadable file there is an error rather than a reason to look further.
+// The map is loaded once and kept, so callers can report a failure after
+// building a scene without decoding the file again.
+pub fn earth_map() -> Result<&'static Image, String> {
+    static EARTH_MAP: OnceLock<Result<Image, String>> = OnceLock::new();
+    let map = EARTH_MAP.get_or_init(|| {
+        let mut candidates = Vec::new();
+        if let Some(dir) = env::var_os("RTW_IMAGES") {
+            candidates.push(PathBuf::from(dir).join("earthmap.ppm"));
+        }
+        candidates.push(PathBuf::from("images").join("earthmap.ppm"));
+
+        let Some(path) = candidates.iter().find(|path| path.exists()) else {
+            return Err(String::from(
+                "earthmap.ppm is in neither $RTW_IMAGES nor images/",
+            ));
+        };
+        Image::load(path).map_err(|err| format!("{}: {err}", path.display()))
+    });
+    map.as_ref().map_err(Clone::clone)
+}
+
+// Without the earth map the texture is empty, which renders as solid cyan.
+fn earth_texture() -> Arc<dyn Texture> {
+    let image = earth_map().cloned().unwrap_or_else(|_| Image::new(0, 0));
+    Arc::new(ImageTexture::new(image))
+}
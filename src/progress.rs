@@ -0,0 +1,151 @@
+use std::{
+    io::{self, IsTerminal, Write},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use raytracing::{ProgressCallback, RenderProgress};
+
+const WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// A progress bar on stderr for a render in one or more passes. It is only
+// drawn when stderr is a terminal, but keeps count of the rays either way
+// for the summary at the end.
+pub struct ProgressBar {
+    unit: &'static str,
+    draw: bool,
+    start: Instant,
+    state: Mutex<State>,
+}
+
+struct State {
+    pass: usize,
+    passes: usize,
+    // The rays of the passes before the current one.
+    paths: u64,
+    rays: u64,
+    current: RenderProgress,
+    last_draw: Option<Instant>,
+}
+
+impl ProgressBar {
+    // A bar counting `unit`s, such as rows or tiles.
+    pub fn new(unit: &'static str) -> Arc<ProgressBar> {
+        Arc::new(ProgressBar {
+            unit,
+            draw: io::stderr().is_terminal(),
+            start: Instant::now(),
+            state: Mutex::new(State {
+                pass: 0,
+                passes: 1,
+                paths: 0,
+                rays: 0,
+                current: RenderProgress::default(),
+                last_draw: None,
+            }),
+        })
+    }
+
+    pub fn callback(self: &Arc<Self>) -> ProgressCallback {
+        let bar = Arc::clone(self);
+        Arc::new(move |progress| bar.update(progress))
+    }
+
+    // Moves on to pass `pass` of `passes`, counting from zero.
+    pub fn start_pass(&self, pass: usize, passes: usize) {
+        let mut state = self.state.lock().expect("progress bar poisoned");
+        state.paths += state.current.paths;
+        state.rays += state.current.rays;
+        state.current = RenderProgress::default();
+        state.pass = pass;
+        state.passes = passes;
+    }
+
+    pub fn update(&self, progress: &RenderProgress) {
+        let mut state = self.state.lock().expect("progress bar poisoned");
+        state.current = *progress;
+        if !self.draw {
+            return;
+        }
+        let finished = progress.done == progress.total;
+        if !finished
+            && state
+                .last_draw
+                .is_some_and(|t| t.elapsed() < REDRAW_INTERVAL)
+        {
+            return;
+        }
+        state.last_draw = Some(Instant::now());
+
+        let pass_fraction = progress.fraction();
+        let fraction = (state.pass as f64 + pass_fraction) / state.passes as f64;
+        let filled = (fraction * WIDTH as f64) as usize;
+        let elapsed = self.start.elapsed();
+        let eta = if fraction > 0.0 {
+            format_duration(elapsed.mul_f64((1.0 - fraction) / fraction))
+        } else {
+            String::from("--:--")
+        };
+        let pass = if state.passes > 1 {
+            format!("pass {}/{}  ", state.pass + 1, state.passes)
+        } else {
+            String::new()
+        };
+
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:>3}%  {pass}{}/{} {}  {}  ETA {eta} ",
+            "#".repeat(filled),
+            "-".repeat(WIDTH - filled),
+            (fraction * 100.0) as usize,
+            progress.done,
+            progress.total,
+            self.unit,
+            format_rate(progress.rays_per_second()),
+        );
+        let _ = stderr.flush();
+    }
+
+    // Erases the bar, so that other output starts on a clean line.
+    pub fn clear(&self) {
+        let mut state = self.state.lock().expect("progress bar poisoned");
+        if self.draw && state.last_draw.take().is_some() {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r{:100}\r", "");
+            let _ = stderr.flush();
+        }
+    }
+
+    // The rays of all passes so far, over the time since the bar was made.
+    pub fn summary(&self) -> RenderProgress {
+        let state = self.state.lock().expect("progress bar poisoned");
+        RenderProgress {
+            done: state.current.done,
+            total: state.current.total,
+            paths: state.paths + state.current.paths,
+            rays: state.rays + state.current.rays,
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
+
+pub fn format_rate(rays_per_second: f64) -> String {
+    match rays_per_second {
+        r if r >= 1e9 => format!("{:.2} Grays/s", r / 1e9),
+        r if r >= 1e6 => format!("{:.2} Mrays/s", r / 1e6),
+        r if r >= 1e3 => format!("{:.2} krays/s", r / 1e3),
+        r => format!("{r:.0} rays/s"),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+// A parallelogram with corner `q` spanned by the edge vectors `u` and `v`.
+pub struct Quad {
+    q: Vec3,
+    u: Vec3,
+    v: Vec3,
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Quad {
+    pub fn new(q: Vec3, u: Vec3, v: Vec3, mat: Arc<dyn Material>) -> Self {
+        let n = u.cross(v);
+        let normal = Vec3::unit_vector(n);
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+
+        Quad {
+            q,
+            u,
+            v,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            mat,
+            bbox: Aabb::surrounding(&bbox_diagonal1, &bbox_diagonal2),
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Quad);
+        let denom = self.normal.dot(r.dir);
+        if f64::abs(denom) < 1e-8 {
+            return false;
+        }
+
+        let t = (self.d - self.normal.dot(r.origin)) / denom;
+        if !interval.contains(t) {
+            return false;
+        }
+
+        // Planar coordinates of the hit point in the (u, v) frame.
+        let p = r.at(t);
+        let planar = p - self.q;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = p;
+        rec.u = alpha;
+        rec.v = beta;
+        rec.set_face_normal(r, &self.normal);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,340 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::{self, Aabb},
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+// Every shape in this module is a surface of revolution around the local z
+// axis whose squared radius is a quadratic in z:
+//
+//     x^2 + y^2 = alpha * z^2 + beta * z + gamma
+//
+// so a single solver handles the side wall and the optional end caps.
+struct Quadric {
+    origin: Vec3,
+    basis: Onb,
+    alpha: f64,
+    beta: f64,
+    gamma: f64,
+    z_min: f64,
+    z_max: f64,
+    phi_max: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+}
+
+struct LocalHit {
+    t: f64,
+    p: Vec3,
+    normal: Vec3,
+    u: f64,
+    v: f64,
+}
+
+impl Quadric {
+    fn new(
+        origin: Vec3,
+        axis: Vec3,
+        profile: (f64, f64, f64),
+        z_range: (f64, f64),
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        Quadric {
+            origin,
+            basis: Onb::new(axis),
+            alpha: profile.0,
+            beta: profile.1,
+            gamma: profile.2,
+            z_min: f64::min(z_range.0, z_range.1),
+            z_max: f64::max(z_range.0, z_range.1),
+            phi_max: 2.0 * PI,
+            capped: false,
+            mat,
+        }
+    }
+
+    fn radius_squared(&self, z: f64) -> f64 {
+        (self.alpha * z + self.beta) * z + self.gamma
+    }
+
+    fn set_sweep(&mut self, degrees: f64) {
+        self.phi_max = degrees.to_radians().clamp(0.0, 2.0 * PI);
+    }
+
+    fn set_height_limits(&mut self, z_min: f64, z_max: f64) {
+        self.z_min = f64::min(z_min, z_max);
+        self.z_max = f64::max(z_min, z_max);
+    }
+
+    fn phi(p: Vec3) -> f64 {
+        let phi = f64::atan2(p.y, p.x);
+        if phi < 0.0 {
+            phi + 2.0 * PI
+        } else {
+            phi
+        }
+    }
+
+    fn side_hit(&self, origin: Vec3, dir: Vec3, interval: Interval) -> Option<LocalHit> {
+        let a = dir.x * dir.x + dir.y * dir.y - self.alpha * dir.z * dir.z;
+        let b = 2.0 * (origin.x * dir.x + origin.y * dir.y - self.alpha * origin.z * dir.z)
+            - self.beta * dir.z;
+        let c = origin.x * origin.x + origin.y * origin.y - self.radius_squared(origin.z);
+
+        let (t0, t1) = polynomial::solve_quadratic(a, b, c)?;
+
+        for t in [t0, t1] {
+            if !interval.surrounds(t) {
+                continue;
+            }
+
+            let p = origin + t * dir;
+            if p.z < self.z_min || p.z > self.z_max {
+                continue;
+            }
+
+            let phi = Quadric::phi(p);
+            if phi > self.phi_max {
+                continue;
+            }
+
+            let normal = Vec3::new(2.0 * p.x, 2.0 * p.y, -(2.0 * self.alpha * p.z + self.beta));
+            return Some(LocalHit {
+                t,
+                p,
+                normal,
+                u: phi / self.phi_max,
+                v: (p.z - self.z_min) / (self.z_max - self.z_min),
+            });
+        }
+
+        None
+    }
+
+    fn cap_hit(&self, origin: Vec3, dir: Vec3, z: f64, interval: Interval) -> Option<LocalHit> {
+        if f64::abs(dir.z) < 1e-12 {
+            return None;
+        }
+
+        let radius_squared = self.radius_squared(z);
+        if radius_squared <= 0.0 {
+            return None;
+        }
+
+        let t = (z - origin.z) / dir.z;
+        if !interval.surrounds(t) {
+            return None;
+        }
+
+        let p = origin + t * dir;
+        let dist_squared = p.x * p.x + p.y * p.y;
+        if dist_squared > radius_squared {
+            return None;
+        }
+
+        let phi = Quadric::phi(p);
+        if phi > self.phi_max {
+            return None;
+        }
+
+        let normal = if z == self.z_max {
+            Vec3::new(0.0, 0.0, 1.0)
+        } else {
+            Vec3::new(0.0, 0.0, -1.0)
+        };
+
+        Some(LocalHit {
+            t,
+            p,
+            normal,
+            u: phi / self.phi_max,
+            v: f64::sqrt(dist_squared / radius_squared),
+        })
+    }
+
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Quadric);
+        let origin = self.basis.to_local(r.origin - self.origin);
+        let dir = self.basis.to_local(r.dir);
+
+        let mut closest = self.side_hit(origin, dir, interval);
+        if self.capped {
+            for z in [self.z_min, self.z_max] {
+                let max = closest.as_ref().map_or(interval.max, |h| h.t);
+                if let Some(cap) = self.cap_hit(origin, dir, z, Interval::new(interval.min, max)) {
+                    closest = Some(cap);
+                }
+            }
+        }
+
+        let Some(hit) = closest else {
+            return false;
+        };
+
+        rec.t = hit.t;
+        rec.p = self.origin + self.basis.to_world(hit.p);
+        rec.u = hit.u;
+        rec.v = hit.v;
+        let outward_normal = Vec3::unit_vector(self.basis.to_world(hit.normal));
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let max_radius_squared = f64::max(
+            self.radius_squared(self.z_min),
+            self.radius_squared(self.z_max),
+        );
+        let radius = f64::sqrt(f64::max(0.0, max_radius_squared));
+        let local = Aabb::from_points(
+            Vec3::new(-radius, -radius, self.z_min),
+            Vec3::new(radius, radius, self.z_max),
+        );
+
+        let mut bbox = aabb::EMPTY;
+        for corner in local.corners() {
+            let p = self.origin + self.basis.to_world(corner);
+            bbox = Aabb::surrounding(&bbox, &Aabb::from_points(p, p));
+        }
+
+        bbox
+    }
+}
+
+macro_rules! quadric_shape {
+    ($name:ident) => {
+        impl $name {
+            pub fn with_sweep(mut self, degrees: f64) -> Self {
+                self.surface.set_sweep(degrees);
+                self
+            }
+
+            pub fn with_height_limits(mut self, z_min: f64, z_max: f64) -> Self {
+                self.surface.set_height_limits(z_min, z_max);
+                self
+            }
+        }
+
+        impl Hittable for $name {
+            fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+                self.surface.hit(r, interval, rec)
+            }
+
+            fn bounding_box(&self) -> Aabb {
+                self.surface.bounding_box()
+            }
+        }
+    };
+}
+
+pub struct Cylinder {
+    surface: Quadric,
+}
+
+impl Cylinder {
+    pub fn new(base: Vec3, axis: Vec3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        let radius = f64::max(0.0, radius);
+        Cylinder {
+            surface: Quadric::new(base, axis, (0.0, 0.0, radius * radius), (0.0, height), mat),
+        }
+    }
+
+    pub fn with_caps(mut self) -> Self {
+        self.surface.capped = true;
+        self
+    }
+}
+
+quadric_shape!(Cylinder);
+
+pub struct Cone {
+    surface: Quadric,
+}
+
+impl Cone {
+    pub fn new(base: Vec3, axis: Vec3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        let radius = f64::max(0.0, radius);
+        let height = f64::max(1e-8, height);
+        let k = radius * radius / (height * height);
+        Cone {
+            surface: Quadric::new(
+                base,
+                axis,
+                (k, -2.0 * k * height, radius * radius),
+                (0.0, height),
+                mat,
+            ),
+        }
+    }
+
+    pub fn with_caps(mut self) -> Self {
+        self.surface.capped = true;
+        self
+    }
+}
+
+quadric_shape!(Cone);
+
+pub struct Paraboloid {
+    surface: Quadric,
+}
+
+impl Paraboloid {
+    pub fn new(vertex: Vec3, axis: Vec3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        let radius = f64::max(0.0, radius);
+        let height = f64::max(1e-8, height);
+        Paraboloid {
+            surface: Quadric::new(
+                vertex,
+                axis,
+                (0.0, radius * radius / height, 0.0),
+                (0.0, height),
+                mat,
+            ),
+        }
+    }
+}
+
+quadric_shape!(Paraboloid);
+
+pub struct Hyperboloid {
+    surface: Quadric,
+}
+
+impl Hyperboloid {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        waist_radius: f64,
+        end_radius: f64,
+        height: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let waist_radius = f64::max(0.0, waist_radius);
+        let end_radius = f64::max(waist_radius, end_radius);
+        let half_height = f64::max(1e-8, height / 2.0);
+        let k =
+            (end_radius * end_radius - waist_radius * waist_radius) / (half_height * half_height);
+        Hyperboloid {
+            surface: Quadric::new(
+                center,
+                axis,
+                (k, 0.0, waist_radius * waist_radius),
+                (-half_height, half_height),
+                mat,
+            ),
+        }
+    }
+}
+
+quadric_shape!(Hyperboloid);
@@ -0,0 +1,425 @@
+use std::{f64::consts::PI, fmt, str::FromStr};
+
+use crate::{
+    utils::{mix_seed, random_double},
+    vec3::Vec3,
+};
+
+// The sample dimensions are laid out the same way for every path: the film
+// position, then the lens, then the shutter time, then a block per bounce.
+// Keeping each use at a fixed dimension lets the low-discrepancy samplers
+// stratify it across a pixel's samples.
+pub const PIXEL_DIMENSION: usize = 0;
+pub const LENS_DIMENSION: usize = 2;
+pub const TIME_DIMENSION: usize = 4;
+const BOUNCE_DIMENSION: usize = 5;
+const DIMENSIONS_PER_BOUNCE: usize = 3;
+
+// The first dimension of the block a material scattering at bounce `bounce`
+// draws from; a material may use up to three dimensions.
+pub fn bounce_dimension(bounce: usize) -> usize {
+    BOUNCE_DIMENSION + bounce * DIMENSIONS_PER_BOUNCE
+}
+
+// A source of sample values in [0, 1). Each call draws from the current
+// dimension and moves past it; `start_pixel_sample` goes back to dimension
+// zero of another sample.
+pub trait Sampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, index: usize);
+    fn set_dimension(&mut self, dimension: usize);
+    fn get_1d(&mut self) -> f64;
+    fn get_2d(&mut self) -> (f64, f64);
+}
+
+// Which sampler a render uses. Stratified divides each dimension into one
+// stratum per sample; without jitter the samples sit at the strata centers.
+// Halton and Sobol are low-discrepancy sequences, randomized per pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified {
+        jitter: bool,
+    },
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    // A sampler for `samples_per_pixel` samples of every pixel, randomized
+    // from `seed`.
+    pub fn sampler(&self, samples_per_pixel: usize, seed: u64) -> Box<dyn Sampler> {
+        match *self {
+            SamplerKind::Independent => Box::new(IndependentSampler),
+            SamplerKind::Stratified { jitter } => {
+                Box::new(StratifiedSampler::new(samples_per_pixel, jitter, seed))
+            }
+            SamplerKind::Halton => Box::new(HaltonSampler::new(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(seed)),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "independent" => Ok(SamplerKind::Independent),
+            "stratified" => Ok(SamplerKind::Stratified { jitter: true }),
+            "halton" => Ok(SamplerKind::Halton),
+            "sobol" => Ok(SamplerKind::Sobol),
+            _ => Err(format!(
+                "unknown sampler `{s}` (expected independent, stratified, halton or sobol)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SamplerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified { .. } => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+        };
+        write!(f, "{name}")
+    }
+}
+
+// Uniform random values, independent of the pixel and dimension.
+pub struct IndependentSampler;
+
+impl Sampler for IndependentSampler {
+    fn start_pixel_sample(&mut self, _i: usize, _j: usize, _index: usize) {}
+
+    fn set_dimension(&mut self, _dimension: usize) {}
+
+    fn get_1d(&mut self) -> f64 {
+        random_double()
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (random_double(), random_double())
+    }
+}
+
+// The state shared by the samplers that depend on the pixel, the sample
+// index and the dimension.
+struct SampleState {
+    seed: u64,
+    pixel_seed: u64,
+    index: usize,
+    dimension: usize,
+}
+
+impl SampleState {
+    fn new(seed: u64) -> Self {
+        SampleState {
+            seed,
+            pixel_seed: seed,
+            index: 0,
+            dimension: 0,
+        }
+    }
+
+    fn start_pixel_sample(&mut self, i: usize, j: usize, index: usize) {
+        self.pixel_seed = mix_seed(mix_seed(self.seed, i as u64), j as u64);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    // A hash of the pixel and the current dimension, then moves past
+    // `count` dimensions.
+    fn advance(&mut self, count: usize) -> u64 {
+        let hash = mix_seed(self.pixel_seed, self.dimension as u64);
+        self.dimension += count;
+        hash
+    }
+}
+
+// Each dimension is split into one stratum per sample, and a pixel's samples
+// visit the strata in an order shuffled per pixel and dimension. 2D samples
+// use a grid of strata as close to square as the sample count allows.
+pub struct StratifiedSampler {
+    samples_per_pixel: usize,
+    x_strata: usize,
+    y_strata: usize,
+    jitter: bool,
+    state: SampleState,
+}
+
+impl StratifiedSampler {
+    pub fn new(samples_per_pixel: usize, jitter: bool, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let y_strata = (1..=samples_per_pixel)
+            .take_while(|n| n * n <= samples_per_pixel)
+            .filter(|n| samples_per_pixel.is_multiple_of(*n))
+            .last()
+            .unwrap_or(1);
+
+        StratifiedSampler {
+            samples_per_pixel,
+            x_strata: samples_per_pixel / y_strata,
+            y_strata,
+            jitter,
+            state: SampleState::new(seed),
+        }
+    }
+
+    fn offset(&self) -> f64 {
+        if self.jitter {
+            random_double()
+        } else {
+            0.5
+        }
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, index: usize) {
+        self.state.start_pixel_sample(i, j, index);
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.state.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let n = self.samples_per_pixel;
+        let hash = self.state.advance(1);
+        let stratum = permutation_element(self.state.index % n, n, hash);
+
+        (stratum as f64 + self.offset()) / n as f64
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        let n = self.samples_per_pixel;
+        let hash = self.state.advance(2);
+        let stratum = permutation_element(self.state.index % n, n, hash);
+        let (x, y) = (stratum % self.x_strata, stratum / self.x_strata);
+
+        (
+            (x as f64 + self.offset()) / self.x_strata as f64,
+            (y as f64 + self.offset()) / self.y_strata as f64,
+        )
+    }
+}
+
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307,
+    311,
+];
+
+// The Halton sequence, with dimension d the radical inverse of the sample
+// index in the d-th prime. Every pixel shifts each dimension by its own
+// random offset (a Cranley-Patterson rotation) so that neighboring pixels do
+// not repeat the same pattern. Dimensions past the prime table, which only
+// deep bounces reach, fall back to independent random values.
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new(seed: u64) -> Self {
+        HaltonSampler {
+            state: SampleState::new(seed),
+        }
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, index: usize) {
+        self.state.start_pixel_sample(i, j, index);
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.state.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        let dimension = self.state.dimension;
+        let hash = self.state.advance(1);
+        match PRIMES.get(dimension) {
+            Some(&base) => {
+                let value = radical_inverse(base, self.state.index as u64) + to_unit(hash);
+                let value = if value >= 1.0 { value - 1.0 } else { value };
+                value.min(ONE_MINUS_EPSILON)
+            }
+            None => random_double(),
+        }
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        (self.get_1d(), self.get_1d())
+    }
+}
+
+fn radical_inverse(base: u64, mut index: u64) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut inv_base_n = 1.0;
+    let mut reversed = 0;
+    while index > 0 {
+        reversed = reversed * base + index % base;
+        index /= base;
+        inv_base_n *= inv_base;
+    }
+
+    f64::min(reversed as f64 * inv_base_n, ONE_MINUS_EPSILON)
+}
+
+// The first two dimensions of the Sobol sequence, Owen-scrambled with a hash
+// of the pixel and dimension. Each pair of dimensions also shuffles the order
+// of the sample indices with its own scramble, which decorrelates the pairs
+// without needing direction numbers for higher dimensions (Burley,
+// "Practical Hash-based Owen Scrambling", 2020).
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new(seed: u64) -> Self {
+        SobolSampler {
+            state: SampleState::new(seed),
+        }
+    }
+
+    fn sample(&mut self, count: usize) -> (f64, f64) {
+        let hash = self.state.advance(count);
+        let seed = hash as u32;
+        let index = nested_uniform_scramble(self.state.index as u32, seed);
+        let x = nested_uniform_scramble(sobol(index, 0), mix_seed(hash, 0) as u32);
+        let y = nested_uniform_scramble(sobol(index, 1), mix_seed(hash, 1) as u32);
+
+        (to_unit32(x), to_unit32(y))
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn start_pixel_sample(&mut self, i: usize, j: usize, index: usize) {
+        self.state.start_pixel_sample(i, j, index);
+    }
+
+    fn set_dimension(&mut self, dimension: usize) {
+        self.state.dimension = dimension;
+    }
+
+    fn get_1d(&mut self) -> f64 {
+        self.sample(1).0
+    }
+
+    fn get_2d(&mut self) -> (f64, f64) {
+        self.sample(2)
+    }
+}
+
+fn sobol(index: u32, dimension: usize) -> u32 {
+    if dimension == 0 {
+        return index.reverse_bits();
+    }
+
+    // Direction numbers for x + 1, the second dimension's polynomial.
+    let mut result = 0;
+    let mut direction = 1 << 31;
+    let mut index = index;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    result
+}
+
+fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// Scrambles each bit depending only on the bits below it; applied to
+// reversed bits this is an Owen scramble.
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x ^= x.wrapping_mul(0x3d20_adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x0552_6c56);
+    x ^= x.wrapping_mul(0x53a2_2864);
+    x
+}
+
+// Element `i` of a random permutation of 0..n chosen by `seed` (Kensler,
+// "Correlated Multi-Jittered Sampling", 2013).
+fn permutation_element(i: usize, n: usize, seed: u64) -> usize {
+    let (l, p) = (n as u32, seed as u32);
+    let mut w = l.wrapping_sub(1);
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+
+    let mut i = i as u32;
+    loop {
+        i ^= p;
+        i = i.wrapping_mul(0xe170_893d);
+        i ^= p >> 16;
+        i ^= (i & w) >> 4;
+        i ^= p >> 8;
+        i = i.wrapping_mul(0x0929_eb3f);
+        i ^= p >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | p >> 27);
+        i = i.wrapping_mul(0x6935_fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dc_b303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e50_1cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860_a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < l {
+            break;
+        }
+    }
+
+    (i.wrapping_add(p) % l) as usize
+}
+
+const ONE_MINUS_EPSILON: f64 = 1.0 - f64::EPSILON / 2.0;
+
+fn to_unit(hash: u64) -> f64 {
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+fn to_unit32(x: u32) -> f64 {
+    x as f64 / (1u64 << 32) as f64
+}
+
+// A uniformly distributed unit vector from a 2D sample.
+pub fn sample_unit_vector(u: (f64, f64)) -> Vec3 {
+    let z = 1.0 - 2.0 * u.0;
+    let r = f64::sqrt(f64::max(0.0, 1.0 - z * z));
+    let phi = 2.0 * PI * u.1;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// A uniformly distributed point in the unit disk from a 2D sample, using
+// Shirley's concentric mapping so that nearby samples stay nearby.
+pub fn sample_unit_disk(u: (f64, f64)) -> Vec3 {
+    let (a, b) = (2.0 * u.0 - 1.0, 2.0 * u.1 - 1.0);
+    if a == 0.0 && b == 0.0 {
+        return Vec3::default();
+    }
+
+    let (r, theta) = if f64::abs(a) > f64::abs(b) {
+        (a, PI / 4.0 * (b / a))
+    } else {
+        (b, PI / 2.0 - PI / 4.0 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
@@ -0,0 +1,1040 @@
+use std::{cell::Cell, collections::HashMap, error::Error, fmt, fs, io, path::Path, sync::Arc};
+
+use crate::{
+    aabb::Aabb,
+    camera::{
+        AdaptiveSampling, Aperture, Background, CameraBuilder, CameraError, CropWindow,
+        FisheyeMapping, PhysicalCamera, Projection,
+    },
+    constant_medium::ConstantMedium,
+    csg::{Csg, CsgOp},
+    cuboid::Cuboid,
+    disk::Disk,
+    film::Filter,
+    heightfield::Heightfield,
+    hittable::{self, Hittable, HittableList},
+    image::Image,
+    json::{self, Value, ValueKind},
+    material::{self, Dielectric, DiffuseLight, Isotropic, Lambertian, Material, Metal},
+    plane::Plane,
+    quad::Quad,
+    quadric::{Cone, Cylinder, Hyperboloid, Paraboloid},
+    sampler::SamplerKind,
+    sdf::{self, DistanceFn, SdfObject},
+    sphere::Sphere,
+    texture::{CheckerTexture, ImageTexture, NoiseTexture, SolidColor, Texture},
+    torus::Torus,
+    transform::Transform,
+    vec3::Vec3,
+};
+
+pub struct Scene {
+    pub camera: CameraBuilder,
+    pub world: HittableList,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Invalid {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "{err}"),
+            SceneError::Invalid {
+                line,
+                column,
+                message,
+            } => write!(f, "{line}:{column}: {message}"),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(err: io::Error) -> Self {
+        SceneError::Io(err)
+    }
+}
+
+impl From<json::ParseError> for SceneError {
+    fn from(err: json::ParseError) -> Self {
+        SceneError::Invalid {
+            line: err.line,
+            column: err.column,
+            message: err.message,
+        }
+    }
+}
+
+// Loads a JSON scene description. Relative image paths inside the file are
+// resolved against the file's own directory.
+pub fn load(path: impl AsRef<Path>) -> Result<Scene, SceneError> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    parse(&source, path.parent().unwrap_or(Path::new(".")))
+}
+
+pub fn parse(source: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let root = json::parse(source)?;
+    let fields = Fields::new(&root, &["camera", "textures", "materials", "objects"])?;
+
+    let mut loader = Loader {
+        base_dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        material_count: Cell::new(0),
+    };
+
+    let camera = match fields.get("camera") {
+        Some(v) => camera_builder(v, &loader)?,
+        None => CameraBuilder::default(),
+    };
+
+    if let Some(textures) = fields.get("textures") {
+        for (name, v) in object_members(textures)? {
+            let texture = loader.texture_definition(v)?;
+            loader.textures.insert(name.clone(), texture);
+        }
+    }
+
+    if let Some(materials) = fields.get("materials") {
+        for (name, v) in object_members(materials)? {
+            let material = loader.material_definition(v)?;
+            loader.materials.insert(name.clone(), material);
+        }
+    }
+
+    // Objects are numbered from 1 in the order they are listed, for the
+    // object ID AOV.
+    let mut world = HittableList::new();
+    if let Some(objects) = fields.get("objects") {
+        for (n, v) in array_items(objects)?.iter().enumerate() {
+            let object = loader.object(v)?;
+            world.add(Arc::new(hittable::Labeled::new(object, n as u32 + 1)));
+        }
+    }
+
+    Ok(Scene { camera, world })
+}
+
+fn invalid(v: &Value, message: impl Into<String>) -> SceneError {
+    SceneError::Invalid {
+        line: v.line,
+        column: v.column,
+        message: message.into(),
+    }
+}
+
+fn object_members(v: &Value) -> Result<&[(String, Value)], SceneError> {
+    match &v.kind {
+        ValueKind::Object(members) => Ok(members),
+        _ => Err(invalid(
+            v,
+            format!("expected an object, found {}", v.type_name()),
+        )),
+    }
+}
+
+fn array_items(v: &Value) -> Result<&[Value], SceneError> {
+    match &v.kind {
+        ValueKind::Array(items) => Ok(items),
+        _ => Err(invalid(
+            v,
+            format!("expected an array, found {}", v.type_name()),
+        )),
+    }
+}
+
+fn number(v: &Value) -> Result<f64, SceneError> {
+    match v.kind {
+        ValueKind::Number(n) => Ok(n),
+        _ => Err(invalid(
+            v,
+            format!("expected a number, found {}", v.type_name()),
+        )),
+    }
+}
+
+fn positive_integer(v: &Value) -> Result<i32, SceneError> {
+    let n = number(v)?;
+    if n.fract() != 0.0 || n < 1.0 || n > i32::MAX as f64 {
+        return Err(invalid(v, "expected a positive integer"));
+    }
+    Ok(n as i32)
+}
+
+fn string(v: &Value) -> Result<&str, SceneError> {
+    match &v.kind {
+        ValueKind::String(s) => Ok(s),
+        _ => Err(invalid(
+            v,
+            format!("expected a string, found {}", v.type_name()),
+        )),
+    }
+}
+
+fn boolean(v: &Value) -> Result<bool, SceneError> {
+    match v.kind {
+        ValueKind::Bool(b) => Ok(b),
+        _ => Err(invalid(
+            v,
+            format!("expected a boolean, found {}", v.type_name()),
+        )),
+    }
+}
+
+fn vec3(v: &Value) -> Result<Vec3, SceneError> {
+    match array_items(v)? {
+        [x, y, z] => Ok(Vec3::new(number(x)?, number(y)?, number(z)?)),
+        _ => Err(invalid(v, "expected an array of three numbers")),
+    }
+}
+
+fn number_pair(v: &Value) -> Result<(f64, f64), SceneError> {
+    match array_items(v)? {
+        [a, b] => Ok((number(a)?, number(b)?)),
+        _ => Err(invalid(v, "expected an array of two numbers")),
+    }
+}
+
+fn kind(v: &Value) -> Result<&str, SceneError> {
+    let members = object_members(v)?;
+    match members.iter().find(|(k, _)| k == "type") {
+        Some((_, t)) => string(t),
+        None => Err(invalid(v, "missing field `type`")),
+    }
+}
+
+// The members of a JSON object, checked against the keys a given kind of
+// entry accepts so that typos are reported instead of silently ignored.
+struct Fields<'a> {
+    value: &'a Value,
+    members: &'a [(String, Value)],
+}
+
+impl<'a> Fields<'a> {
+    fn new(value: &'a Value, allowed: &[&str]) -> Result<Self, SceneError> {
+        let members = object_members(value)?;
+        if let Some((key, v)) = members.iter().find(|(k, _)| !allowed.contains(&k.as_str())) {
+            return Err(invalid(v, format!("unknown field `{key}`")));
+        }
+
+        Ok(Fields { value, members })
+    }
+
+    fn get(&self, key: &str) -> Option<&'a Value> {
+        self.members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn required(&self, key: &str) -> Result<&'a Value, SceneError> {
+        self.get(key)
+            .ok_or_else(|| invalid(self.value, format!("missing field `{key}`")))
+    }
+
+    fn number(&self, key: &str) -> Result<f64, SceneError> {
+        number(self.required(key)?)
+    }
+
+    fn number_or(&self, key: &str, default: f64) -> Result<f64, SceneError> {
+        self.get(key).map_or(Ok(default), number)
+    }
+
+    fn vec3(&self, key: &str) -> Result<Vec3, SceneError> {
+        vec3(self.required(key)?)
+    }
+
+    fn vec3_or(&self, key: &str, default: Vec3) -> Result<Vec3, SceneError> {
+        self.get(key).map_or(Ok(default), vec3)
+    }
+
+    fn bool_or(&self, key: &str, default: bool) -> Result<bool, SceneError> {
+        self.get(key).map_or(Ok(default), boolean)
+    }
+}
+
+fn camera_builder(v: &Value, loader: &Loader) -> Result<CameraBuilder, SceneError> {
+    let fields = Fields::new(
+        v,
+        &[
+            "aspect_ratio",
+            "image_width",
+            "samples_per_pixel",
+            "max_depth",
+            "vfov",
+            "look_from",
+            "look_at",
+            "v_up",
+            "defocus_angle",
+            "focus_dist",
+            "background",
+            "projection",
+            "physical",
+            "aperture",
+            "cat_eye",
+            "filter",
+            "sampler",
+            "adaptive",
+            "crop",
+        ],
+    )?;
+
+    let mut camera = CameraBuilder::new();
+    for (key, value) in fields.members {
+        camera = match key.as_str() {
+            "aspect_ratio" => camera.aspect_ratio(number(value)?),
+            "image_width" => camera.image_width(positive_integer(value)?),
+            "samples_per_pixel" => camera.samples_per_pixel(positive_integer(value)?),
+            "max_depth" => camera.max_depth(positive_integer(value)?),
+            "vfov" => camera.vfov(number(value)?),
+            "look_from" => camera.look_from(vec3(value)?),
+            "look_at" => camera.look_at(vec3(value)?),
+            "v_up" => camera.v_up(vec3(value)?),
+            "defocus_angle" => camera.defocus_angle(number(value)?),
+            "focus_dist" => camera.focus_dist(number(value)?),
+            "background" => camera.background(background(value)?),
+            "projection" => camera.projection(projection(value)?),
+            "physical" => camera.physical(physical_camera(value)?),
+            "aperture" => camera.aperture(aperture(value, loader)?),
+            "cat_eye" => camera.cat_eye(number(value)?),
+            "filter" => camera.filter(filter(value)?),
+            "sampler" => camera.sampler(sampler(value)?),
+            "adaptive" => camera.adaptive(adaptive(value)?),
+            "crop" => camera.crop(crop(value)?),
+            other => return Err(invalid(value, format!("unknown field `{other}`"))),
+        };
+    }
+
+    // Report invalid combinations here, where the camera is written, rather
+    // than when rendering starts.
+    camera.build().map_err(|err| {
+        let key = match err {
+            CameraError::InvalidImageWidth(_) => "image_width",
+            CameraError::InvalidSamplesPerPixel(_) => "samples_per_pixel",
+            CameraError::InvalidMaxDepth(_) => "max_depth",
+            CameraError::InvalidAspectRatio(_) => "aspect_ratio",
+            CameraError::InvalidVfov(_) => "vfov",
+            CameraError::InvalidDefocusAngle(_) => "defocus_angle",
+            CameraError::InvalidFocusDist(_) => "focus_dist",
+            CameraError::InvalidViewHeight(_)
+            | CameraError::InvalidFisheyeFov(_)
+            | CameraError::InvalidEyeSeparation(_) => "projection",
+            CameraError::InvalidPhysicalParameter { .. } => "physical",
+            CameraError::InvalidApertureBlades(_) | CameraError::EmptyApertureMask => "aperture",
+            CameraError::InvalidCatEye(_) => "cat_eye",
+            CameraError::InvalidFilterRadius(_) => "filter",
+            CameraError::InvalidMinSamples { .. } | CameraError::InvalidNoiseThreshold(_) => {
+                "adaptive"
+            }
+            CameraError::InvalidCropWindow(_) => "crop",
+            CameraError::LookFromEqualsLookAt => "look_at",
+            CameraError::VupParallelToView => "v_up",
+        };
+        invalid(fields.get(key).unwrap_or(v), err.to_string())
+    })?;
+
+    Ok(camera)
+}
+
+fn aperture(v: &Value, loader: &Loader) -> Result<Aperture, SceneError> {
+    if let ValueKind::String(name) = &v.kind {
+        return match name.as_str() {
+            "disk" => Ok(Aperture::Disk),
+            _ => Err(invalid(v, format!("unknown aperture `{name}`"))),
+        };
+    }
+
+    match kind(v)? {
+        "disk" => {
+            Fields::new(v, &["type"])?;
+            Ok(Aperture::Disk)
+        }
+        "polygon" => {
+            let fields = Fields::new(v, &["type", "blades", "rotation"])?;
+            Ok(Aperture::Polygon {
+                blades: positive_integer(fields.required("blades")?)? as u32,
+                rotation: fields.number_or("rotation", 0.0)?,
+            })
+        }
+        "mask" => {
+            let fields = Fields::new(v, &["type", "image"])?;
+            Ok(Aperture::Mask(Arc::new(
+                loader.image(fields.required("image")?)?,
+            )))
+        }
+        other => Err(invalid(v, format!("unknown aperture `{other}`"))),
+    }
+}
+
+// A reconstruction filter by name, or an object that may override the
+// radius and shape parameters.
+fn filter(v: &Value) -> Result<Filter, SceneError> {
+    let (name, fields) = match &v.kind {
+        ValueKind::String(name) => (name.as_str(), None),
+        _ => {
+            let keys = match kind(v)? {
+                "gaussian" => &["type", "radius", "alpha"][..],
+                "mitchell" => &["type", "radius", "b", "c"][..],
+                "lanczos" => &["type", "radius", "tau"][..],
+                _ => &["type", "radius"][..],
+            };
+            (kind(v)?, Some(Fields::new(v, keys)?))
+        }
+    };
+    let param = |key: &str, default: f64| match &fields {
+        Some(fields) => fields.number_or(key, default),
+        None => Ok(default),
+    };
+
+    match name {
+        "box" => Ok(Filter::Box {
+            radius: param("radius", 0.5)?,
+        }),
+        "tent" => Ok(Filter::Tent {
+            radius: param("radius", 1.0)?,
+        }),
+        "gaussian" => Ok(Filter::Gaussian {
+            radius: param("radius", 1.5)?,
+            alpha: param("alpha", 2.0)?,
+        }),
+        "mitchell" => Ok(Filter::Mitchell {
+            radius: param("radius", 2.0)?,
+            b: param("b", 1.0 / 3.0)?,
+            c: param("c", 1.0 / 3.0)?,
+        }),
+        "lanczos" => Ok(Filter::Lanczos {
+            radius: param("radius", 3.0)?,
+            tau: param("tau", 3.0)?,
+        }),
+        other => Err(invalid(v, format!("unknown filter `{other}`"))),
+    }
+}
+
+fn physical_camera(v: &Value) -> Result<PhysicalCamera, SceneError> {
+    let fields = Fields::new(
+        v,
+        &[
+            "focal_length",
+            "sensor_width",
+            "sensor_height",
+            "f_stop",
+            "shutter_time",
+            "iso",
+        ],
+    )?;
+    let defaults = PhysicalCamera::default();
+
+    Ok(PhysicalCamera {
+        focal_length: fields.number_or("focal_length", defaults.focal_length)?,
+        sensor_width: fields.number_or("sensor_width", defaults.sensor_width)?,
+        sensor_height: fields.number_or("sensor_height", defaults.sensor_height)?,
+        f_stop: fields.number_or("f_stop", defaults.f_stop)?,
+        shutter_time: fields.number_or("shutter_time", defaults.shutter_time)?,
+        iso: fields.number_or("iso", defaults.iso)?,
+    })
+}
+
+// A sampler by name, or an object; only `stratified` takes a parameter.
+fn sampler(v: &Value) -> Result<SamplerKind, SceneError> {
+    if let ValueKind::String(name) = &v.kind {
+        return name.parse().map_err(|err| invalid(v, err));
+    }
+
+    match kind(v)? {
+        "stratified" => {
+            let fields = Fields::new(v, &["type", "jitter"])?;
+            Ok(SamplerKind::Stratified {
+                jitter: fields.bool_or("jitter", true)?,
+            })
+        }
+        name => {
+            let sampler = name.parse().map_err(|err| invalid(v, err))?;
+            Fields::new(v, &["type"])?;
+            Ok(sampler)
+        }
+    }
+}
+
+fn adaptive(v: &Value) -> Result<AdaptiveSampling, SceneError> {
+    let fields = Fields::new(v, &["min_samples", "noise_threshold"])?;
+    let defaults = AdaptiveSampling::default();
+    Ok(AdaptiveSampling {
+        min_samples: match fields.get("min_samples") {
+            Some(value) => positive_integer(value)?,
+            None => defaults.min_samples,
+        },
+        noise_threshold: fields.number_or("noise_threshold", defaults.noise_threshold)?,
+    })
+}
+
+// A crop window as `{"pixels": [x0, y0, x1, y1]}` or, in fractions of the
+// image size, `{"window": [x0, y0, x1, y1]}`.
+fn crop(v: &Value) -> Result<CropWindow, SceneError> {
+    let fields = Fields::new(v, &["pixels", "window"])?;
+    let corners = |value: &Value| -> Result<[f64; 4], SceneError> {
+        match array_items(value)? {
+            [x0, y0, x1, y1] => Ok([number(x0)?, number(y0)?, number(x1)?, number(y1)?]),
+            _ => Err(invalid(value, "expected an array of four numbers")),
+        }
+    };
+
+    match (fields.get("pixels"), fields.get("window")) {
+        (Some(pixels), None) => {
+            let [x0, y0, x1, y1] = corners(pixels)?;
+            if [x0, y0, x1, y1]
+                .iter()
+                .any(|c| c.fract() != 0.0 || *c < 0.0)
+            {
+                return Err(invalid(
+                    pixels,
+                    "pixel corners must be non-negative integers",
+                ));
+            }
+            Ok(CropWindow::Pixels {
+                x0: x0 as usize,
+                y0: y0 as usize,
+                x1: x1 as usize,
+                y1: y1 as usize,
+            })
+        }
+        (None, Some(window)) => {
+            let [x0, y0, x1, y1] = corners(window)?;
+            Ok(CropWindow::Normalized { x0, y0, x1, y1 })
+        }
+        _ => Err(invalid(v, "expected exactly one of `pixels` and `window`")),
+    }
+}
+
+// A projection without parameters may be given by name alone.
+fn projection(v: &Value) -> Result<Projection, SceneError> {
+    if let ValueKind::String(name) = &v.kind {
+        return match name.as_str() {
+            "perspective" => Ok(Projection::Perspective),
+            "equirectangular" => Ok(Projection::Equirectangular),
+            "fisheye" => Ok(Projection::Fisheye {
+                mapping: FisheyeMapping::Equidistant,
+                fov: 180.0,
+            }),
+            "orthographic" | "stereo_equirectangular" => Err(invalid(
+                v,
+                format!("projection `{name}` needs parameters; use an object"),
+            )),
+            _ => Err(invalid(v, format!("unknown projection `{name}`"))),
+        };
+    }
+
+    match kind(v)? {
+        "perspective" => {
+            Fields::new(v, &["type"])?;
+            Ok(Projection::Perspective)
+        }
+        "orthographic" => {
+            let fields = Fields::new(v, &["type", "view_height"])?;
+            Ok(Projection::Orthographic {
+                view_height: fields.number("view_height")?,
+            })
+        }
+        "fisheye" => {
+            let fields = Fields::new(v, &["type", "mapping", "fov"])?;
+            let mapping = match fields.get("mapping") {
+                None => FisheyeMapping::Equidistant,
+                Some(m) => match string(m)? {
+                    "equidistant" => FisheyeMapping::Equidistant,
+                    "equisolid" => FisheyeMapping::Equisolid,
+                    other => return Err(invalid(m, format!("unknown fisheye mapping `{other}`"))),
+                },
+            };
+            Ok(Projection::Fisheye {
+                mapping,
+                fov: fields.number_or("fov", 180.0)?,
+            })
+        }
+        "equirectangular" => {
+            Fields::new(v, &["type"])?;
+            Ok(Projection::Equirectangular)
+        }
+        "stereo_equirectangular" => {
+            let fields = Fields::new(v, &["type", "eye_separation"])?;
+            Ok(Projection::StereoEquirectangular {
+                eye_separation: fields.number("eye_separation")?,
+            })
+        }
+        other => Err(invalid(v, format!("unknown projection `{other}`"))),
+    }
+}
+
+// Either the string "sky" for the default gradient or a solid color.
+fn background(v: &Value) -> Result<Background, SceneError> {
+    match &v.kind {
+        ValueKind::String(name) if name == "sky" => Ok(Background::Sky),
+        ValueKind::String(name) => Err(invalid(v, format!("unknown background `{name}`"))),
+        _ => Ok(Background::Solid(vec3(v)?)),
+    }
+}
+
+struct Loader<'a> {
+    base_dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    material_count: Cell<u32>,
+}
+
+impl Loader<'_> {
+    fn image(&self, v: &Value) -> Result<Image, SceneError> {
+        let path = self.base_dir.join(string(v)?);
+        Image::load(&path).map_err(|err| invalid(v, format!("{}: {err}", path.display())))
+    }
+
+    // A texture given either by name or as an inline color.
+    fn texture(&self, v: &Value) -> Result<Arc<dyn Texture>, SceneError> {
+        match &v.kind {
+            ValueKind::String(name) => self
+                .textures
+                .get(name)
+                .cloned()
+                .ok_or_else(|| invalid(v, format!("unknown texture `{name}`"))),
+            _ => Ok(Arc::new(SolidColor::new(vec3(v)?))),
+        }
+    }
+
+    fn texture_definition(&self, v: &Value) -> Result<Arc<dyn Texture>, SceneError> {
+        match kind(v)? {
+            "solid" => {
+                let fields = Fields::new(v, &["type", "color"])?;
+                Ok(Arc::new(SolidColor::new(fields.vec3("color")?)))
+            }
+            "checker" => {
+                let fields = Fields::new(v, &["type", "scale", "even", "odd"])?;
+                Ok(Arc::new(CheckerTexture::new(
+                    fields.number("scale")?,
+                    self.texture(fields.required("even")?)?,
+                    self.texture(fields.required("odd")?)?,
+                )))
+            }
+            "image" => {
+                let fields = Fields::new(v, &["type", "path"])?;
+                Ok(Arc::new(ImageTexture::new(
+                    self.image(fields.required("path")?)?,
+                )))
+            }
+            "noise" => {
+                let fields = Fields::new(v, &["type", "scale"])?;
+                Ok(Arc::new(NoiseTexture::new(fields.number_or("scale", 1.0)?)))
+            }
+            other => Err(invalid(v, format!("unknown texture type `{other}`"))),
+        }
+    }
+
+    // A material given either by name or as an inline definition.
+    fn material(&self, v: &Value) -> Result<Arc<dyn Material>, SceneError> {
+        match &v.kind {
+            ValueKind::String(name) => self
+                .materials
+                .get(name)
+                .cloned()
+                .ok_or_else(|| invalid(v, format!("unknown material `{name}`"))),
+            _ => self.material_definition(v),
+        }
+    }
+
+    // Materials are numbered from 1 as they are defined, named ones first,
+    // for the material ID AOV.
+    fn material_definition(&self, v: &Value) -> Result<Arc<dyn Material>, SceneError> {
+        let material = self.unlabeled_material(v)?;
+        let id = self.material_count.get() + 1;
+        self.material_count.set(id);
+        Ok(Arc::new(material::Labeled::new(material, id)))
+    }
+
+    fn unlabeled_material(&self, v: &Value) -> Result<Arc<dyn Material>, SceneError> {
+        match kind(v)? {
+            "lambertian" => {
+                let fields = Fields::new(v, &["type", "albedo"])?;
+                Ok(Arc::new(Lambertian::from_texture(
+                    self.texture(fields.required("albedo")?)?,
+                )))
+            }
+            "metal" => {
+                let fields = Fields::new(v, &["type", "albedo", "fuzz"])?;
+                Ok(Arc::new(Metal::new(
+                    fields.vec3("albedo")?,
+                    fields.number_or("fuzz", 0.0)?,
+                )))
+            }
+            "dielectric" => {
+                let fields = Fields::new(v, &["type", "refraction_index"])?;
+                Ok(Arc::new(Dielectric::new(
+                    fields.number("refraction_index")?,
+                )))
+            }
+            "diffuse_light" => {
+                let fields = Fields::new(v, &["type", "emit"])?;
+                Ok(Arc::new(DiffuseLight::from_texture(
+                    self.texture(fields.required("emit")?)?,
+                )))
+            }
+            "isotropic" => {
+                let fields = Fields::new(v, &["type", "albedo"])?;
+                Ok(Arc::new(Isotropic::from_texture(
+                    self.texture(fields.required("albedo")?)?,
+                )))
+            }
+            other => Err(invalid(v, format!("unknown material type `{other}`"))),
+        }
+    }
+
+    fn object(&self, v: &Value) -> Result<Arc<dyn Hittable>, SceneError> {
+        let up = Vec3::new(0.0, 1.0, 0.0);
+
+        let object_kind = kind(v)?;
+        let object: Arc<dyn Hittable> = match object_kind {
+            "sphere" => {
+                let f = Fields::new(v, &object_keys(&["center", "radius", "material"]))?;
+                Arc::new(Sphere::new(
+                    f.vec3("center")?,
+                    f.number("radius")?,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "plane" => {
+                let f = Fields::new(v, &object_keys(&["point", "normal", "material"]))?;
+                Arc::new(Plane::new(
+                    f.vec3("point")?,
+                    f.vec3_or("normal", up)?,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "quad" => {
+                let f = Fields::new(v, &object_keys(&["corner", "u", "v", "material"]))?;
+                Arc::new(Quad::new(
+                    f.vec3("corner")?,
+                    f.vec3("u")?,
+                    f.vec3("v")?,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "disk" => {
+                let f = Fields::new(v, &object_keys(&["center", "normal", "radius", "material"]))?;
+                let radius = f.number("radius")?;
+                if radius <= 0.0 || radius.is_nan() {
+                    return Err(invalid(f.required("radius")?, "radius must be positive"));
+                }
+                Arc::new(Disk::new(
+                    f.vec3("center")?,
+                    f.vec3_or("normal", up)?,
+                    radius,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "cuboid" => {
+                let f = Fields::new(v, &object_keys(&["min", "max", "material"]))?;
+                Arc::new(Cuboid::new(
+                    f.vec3("min")?,
+                    f.vec3("max")?,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "cylinder" | "cone" | "paraboloid" => {
+                let origin_key = if object_kind == "paraboloid" {
+                    "vertex"
+                } else {
+                    "base"
+                };
+                let mut keys = vec![
+                    origin_key,
+                    "axis",
+                    "radius",
+                    "height",
+                    "sweep",
+                    "height_limits",
+                    "material",
+                ];
+                if object_kind != "paraboloid" {
+                    keys.push("caps");
+                }
+                let f = Fields::new(v, &object_keys(&keys))?;
+                let origin = f.vec3(origin_key)?;
+                let axis = f.vec3_or("axis", up)?;
+                let radius = f.number("radius")?;
+                let height = f.number("height")?;
+                let mat = self.material(f.required("material")?)?;
+                let sweep = f.number_or("sweep", 360.0)?;
+                let limits = f.get("height_limits").map(number_pair).transpose()?;
+                let (z_min, z_max) = limits.unwrap_or((0.0, height));
+                let caps = f.bool_or("caps", false)?;
+
+                match object_kind {
+                    "cylinder" => {
+                        let shape = Cylinder::new(origin, axis, radius, height, mat)
+                            .with_sweep(sweep)
+                            .with_height_limits(z_min, z_max);
+                        Arc::new(if caps { shape.with_caps() } else { shape })
+                    }
+                    "cone" => {
+                        let shape = Cone::new(origin, axis, radius, height, mat)
+                            .with_sweep(sweep)
+                            .with_height_limits(z_min, z_max);
+                        Arc::new(if caps { shape.with_caps() } else { shape })
+                    }
+                    _ => Arc::new(
+                        Paraboloid::new(origin, axis, radius, height, mat)
+                            .with_sweep(sweep)
+                            .with_height_limits(z_min, z_max),
+                    ),
+                }
+            }
+            "hyperboloid" => {
+                let f = Fields::new(
+                    v,
+                    &object_keys(&[
+                        "center",
+                        "axis",
+                        "waist_radius",
+                        "end_radius",
+                        "height",
+                        "sweep",
+                        "height_limits",
+                        "material",
+                    ]),
+                )?;
+                let height = f.number("height")?;
+                let limits = f.get("height_limits").map(number_pair).transpose()?;
+                let (z_min, z_max) = limits.unwrap_or((-height / 2.0, height / 2.0));
+                Arc::new(
+                    Hyperboloid::new(
+                        f.vec3("center")?,
+                        f.vec3_or("axis", up)?,
+                        f.number("waist_radius")?,
+                        f.number("end_radius")?,
+                        height,
+                        self.material(f.required("material")?)?,
+                    )
+                    .with_sweep(f.number_or("sweep", 360.0)?)
+                    .with_height_limits(z_min, z_max),
+                )
+            }
+            "torus" => {
+                let f = Fields::new(
+                    v,
+                    &object_keys(&["center", "axis", "major_radius", "minor_radius", "material"]),
+                )?;
+                Arc::new(Torus::new(
+                    f.vec3("center")?,
+                    f.vec3_or("axis", up)?,
+                    f.number("major_radius")?,
+                    f.number("minor_radius")?,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "heightfield" => {
+                let f = Fields::new(
+                    v,
+                    &object_keys(&["image", "grid", "corner", "size", "smooth", "material"]),
+                )?;
+                let corner = f.vec3("corner")?;
+                let size = f.vec3("size")?;
+                let mat = self.material(f.required("material")?)?;
+                let field = match (f.get("image"), f.get("grid")) {
+                    (Some(path), None) => {
+                        Heightfield::from_image(&self.image(path)?, corner, size, mat)
+                    }
+                    (None, Some(grid)) => {
+                        let rows = array_items(grid)?
+                            .iter()
+                            .map(|row| {
+                                array_items(row)?
+                                    .iter()
+                                    .map(|h| Ok(size.y * number(h)?))
+                                    .collect::<Result<Vec<f64>, SceneError>>()
+                            })
+                            .collect::<Result<Vec<Vec<f64>>, SceneError>>()?;
+                        Heightfield::new(rows, corner, size.x, size.z, mat)
+                    }
+                    _ => return Err(invalid(v, "expected exactly one of `image` or `grid`")),
+                };
+                if f.bool_or("smooth", false)? {
+                    Arc::new(field.with_smooth_normals())
+                } else {
+                    Arc::new(field)
+                }
+            }
+            "sdf" => {
+                let f = Fields::new(v, &object_keys(&["shape", "bounds", "material"]))?;
+                let bounds = f.required("bounds")?;
+                let bbox = match array_items(bounds)? {
+                    [min, max] => Aabb::from_points(vec3(min)?, vec3(max)?),
+                    _ => return Err(invalid(bounds, "expected `[min, max]` corners")),
+                };
+                Arc::new(SdfObject::new(
+                    sdf_shape(f.required("shape")?)?,
+                    bbox,
+                    self.material(f.required("material")?)?,
+                ))
+            }
+            "csg" => {
+                let f = Fields::new(v, &object_keys(&["op", "left", "right"]))?;
+                let op_value = f.required("op")?;
+                let op = match string(op_value)? {
+                    "union" => CsgOp::Union,
+                    "intersection" => CsgOp::Intersection,
+                    "difference" => CsgOp::Difference,
+                    other => return Err(invalid(op_value, format!("unknown CSG op `{other}`"))),
+                };
+                Arc::new(Csg::new(
+                    op,
+                    self.object(f.required("left")?)?,
+                    self.object(f.required("right")?)?,
+                ))
+            }
+            "constant_medium" => {
+                let f = Fields::new(v, &object_keys(&["boundary", "density", "albedo"]))?;
+                Arc::new(ConstantMedium::from_texture(
+                    self.object(f.required("boundary")?)?,
+                    f.number("density")?,
+                    self.texture(f.required("albedo")?)?,
+                ))
+            }
+            "group" => {
+                let f = Fields::new(v, &object_keys(&["objects"]))?;
+                let mut group = HittableList::new();
+                for item in array_items(f.required("objects")?)? {
+                    group.add(self.object(item)?);
+                }
+                Arc::new(group)
+            }
+            other => return Err(invalid(v, format!("unknown object type `{other}`"))),
+        };
+
+        let fields = Fields {
+            value: v,
+            members: object_members(v)?,
+        };
+        match fields.get("transform") {
+            Some(ops) => transform(object, ops),
+            None => Ok(object),
+        }
+    }
+}
+
+// The keys an object of some type accepts, on top of the common ones.
+fn object_keys<'a>(keys: &[&'a str]) -> Vec<&'a str> {
+    let mut all = vec!["type", "transform"];
+    all.extend_from_slice(keys);
+    all
+}
+
+fn transform(object: Arc<dyn Hittable>, ops: &Value) -> Result<Arc<dyn Hittable>, SceneError> {
+    let mut transform = Transform::new(object);
+
+    for op in array_items(ops)? {
+        let members = object_members(op)?;
+        let [(name, arg)] = members else {
+            return Err(invalid(op, "expected an object with a single operation"));
+        };
+
+        transform = match name.as_str() {
+            "translate" => transform.translate(vec3(arg)?),
+            "scale" => {
+                let factors = match arg.kind {
+                    ValueKind::Number(s) => Vec3::new(s, s, s),
+                    _ => vec3(arg)?,
+                };
+                if [factors.x, factors.y, factors.z]
+                    .iter()
+                    .any(|s| *s == 0.0 || !s.is_finite())
+                {
+                    return Err(invalid(arg, "scale factors must be finite and non-zero"));
+                }
+                transform.scale(factors)
+            }
+            "rotate_x" => transform.rotate_x(number(arg)?),
+            "rotate_y" => transform.rotate_y(number(arg)?),
+            "rotate_z" => transform.rotate_z(number(arg)?),
+            "rotate" => {
+                let f = Fields::new(arg, &["axis", "degrees"])?;
+                let axis = f.vec3("axis")?;
+                let degrees = f.number("degrees")?;
+                let finite = [axis.x, axis.y, axis.z, degrees]
+                    .iter()
+                    .all(|v| v.is_finite());
+                if !finite || axis.length_squared() == 0.0 {
+                    return Err(invalid(arg, "rotation axis must be finite and non-zero"));
+                }
+                transform.rotate(axis, degrees)
+            }
+            other => return Err(invalid(arg, format!("unknown transform `{other}`"))),
+        };
+        // Scales can still degenerate in combination, such as two of 1e200.
+        if !transform.is_invertible() {
+            return Err(invalid(op, "transform is not invertible"));
+        }
+    }
+
+    Ok(Arc::new(transform))
+}
+
+fn sdf_shape(v: &Value) -> Result<DistanceFn, SceneError> {
+    match kind(v)? {
+        "sphere" => {
+            let f = Fields::new(v, &["type", "center", "radius"])?;
+            Ok(sdf::sphere(f.vec3("center")?, f.number("radius")?))
+        }
+        "box" => {
+            let f = Fields::new(v, &["type", "center", "half_extents"])?;
+            Ok(sdf::cuboid(f.vec3("center")?, f.vec3("half_extents")?))
+        }
+        "torus" => {
+            let f = Fields::new(v, &["type", "center", "major_radius", "minor_radius"])?;
+            Ok(sdf::torus(
+                f.vec3("center")?,
+                f.number("major_radius")?,
+                f.number("minor_radius")?,
+            ))
+        }
+        "capsule" => {
+            let f = Fields::new(v, &["type", "a", "b", "radius"])?;
+            Ok(sdf::capsule(
+                f.vec3("a")?,
+                f.vec3("b")?,
+                f.number("radius")?,
+            ))
+        }
+        "union" => {
+            let f = Fields::new(v, &["type", "a", "b"])?;
+            Ok(sdf::union(
+                sdf_shape(f.required("a")?)?,
+                sdf_shape(f.required("b")?)?,
+            ))
+        }
+        "smooth_union" => {
+            let f = Fields::new(v, &["type", "a", "b", "k"])?;
+            Ok(sdf::smooth_union(
+                sdf_shape(f.required("a")?)?,
+                sdf_shape(f.required("b")?)?,
+                f.number("k")?,
+            ))
+        }
+        "subtraction" => {
+            let f = Fields::new(v, &["type", "a", "b"])?;
+            Ok(sdf::subtraction(
+                sdf_shape(f.required("a")?)?,
+                sdf_shape(f.required("b")?)?,
+            ))
+        }
+        "repetition" => {
+            let f = Fields::new(v, &["type", "shape", "period"])?;
+            Ok(sdf::repetition(
+                sdf_shape(f.required("shape")?)?,
+                f.vec3("period")?,
+            ))
+        }
+        other => Err(invalid(v, format!("unknown SDF shape `{other}`"))),
+    }
+}
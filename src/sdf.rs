@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    ray::Ray,
+    sphere::Sphere,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+pub type DistanceFn = Arc<dyn Fn(Vec3) -> f64 + Send + Sync>;
+
+const MAX_STEPS: usize = 512;
+const MAX_DISTANCE: f64 = 1e5;
+const SURFACE_EPSILON: f64 = 1e-5;
+const NORMAL_EPSILON: f64 = 1e-4;
+
+pub struct SdfObject {
+    distance: DistanceFn,
+    bbox: Aabb,
+    mat: Arc<dyn Material>,
+}
+
+impl SdfObject {
+    // `distance` must never overestimate the distance to the surface, and
+    // `bbox` must enclose the surface; marching is confined to it.
+    pub fn new(distance: DistanceFn, bbox: Aabb, mat: Arc<dyn Material>) -> Self {
+        SdfObject {
+            distance,
+            bbox,
+            mat,
+        }
+    }
+
+    fn normal(&self, p: Vec3) -> Vec3 {
+        // Tetrahedral central differences: four evaluations instead of six.
+        let h = NORMAL_EPSILON;
+        let k0 = Vec3::new(1.0, -1.0, -1.0);
+        let k1 = Vec3::new(-1.0, -1.0, 1.0);
+        let k2 = Vec3::new(-1.0, 1.0, -1.0);
+        let k3 = Vec3::new(1.0, 1.0, 1.0);
+        let gradient = k0 * (self.distance)(p + k0 * h)
+            + k1 * (self.distance)(p + k1 * h)
+            + k2 * (self.distance)(p + k2 * h)
+            + k3 * (self.distance)(p + k3 * h);
+
+        Vec3::unit_vector(gradient)
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Sdf);
+        let dir_length = r.dir.length();
+        if dir_length == 0.0 {
+            return false;
+        }
+        let Some(range) = self.bbox.clip(r, interval) else {
+            return false;
+        };
+        let t_end = f64::min(range.max, MAX_DISTANCE / dir_length);
+
+        // March from whichever side of the surface the ray starts on, so rays
+        // travelling through the interior (refraction) find the exit as well.
+        let mut t = range.min;
+        let side = (self.distance)(r.at(t)).signum();
+        let mut found = false;
+        for _ in 0..MAX_STEPS {
+            let d = side * (self.distance)(r.at(t));
+            if d < SURFACE_EPSILON * f64::max(1.0, t * dir_length) {
+                found = true;
+                break;
+            }
+            t += d / dir_length;
+            if t > t_end {
+                break;
+            }
+        }
+
+        if !found || !interval.surrounds(t) {
+            return false;
+        }
+
+        rec.t = t;
+        rec.p = r.at(t);
+        let outward_normal = self.normal(rec.p);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(outward_normal);
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+pub fn sphere(center: Vec3, radius: f64) -> DistanceFn {
+    Arc::new(move |p| (p - center).length() - radius)
+}
+
+pub fn cuboid(center: Vec3, half_extents: Vec3) -> DistanceFn {
+    Arc::new(move |p| {
+        let p = p - center;
+        let q = Vec3::new(
+            f64::abs(p.x) - half_extents.x,
+            f64::abs(p.y) - half_extents.y,
+            f64::abs(p.z) - half_extents.z,
+        );
+        let outside = Vec3::new(f64::max(q.x, 0.0), f64::max(q.y, 0.0), f64::max(q.z, 0.0));
+
+        outside.length() + f64::min(f64::max(q.x, f64::max(q.y, q.z)), 0.0)
+    })
+}
+
+// Torus lying in the xz plane around `center`.
+pub fn torus(center: Vec3, major_radius: f64, minor_radius: f64) -> DistanceFn {
+    Arc::new(move |p| {
+        let p = p - center;
+        let ring = f64::sqrt(p.x * p.x + p.z * p.z) - major_radius;
+
+        f64::sqrt(ring * ring + p.y * p.y) - minor_radius
+    })
+}
+
+pub fn capsule(a: Vec3, b: Vec3, radius: f64) -> DistanceFn {
+    Arc::new(move |p| {
+        let pa = p - a;
+        let ba = b - a;
+        let h = (pa.dot(ba) / ba.length_squared()).clamp(0.0, 1.0);
+
+        (pa - ba * h).length() - radius
+    })
+}
+
+pub fn union(a: DistanceFn, b: DistanceFn) -> DistanceFn {
+    Arc::new(move |p| f64::min(a(p), b(p)))
+}
+
+// Polynomial smooth minimum; `k` is the width of the blended region.
+pub fn smooth_union(a: DistanceFn, b: DistanceFn, k: f64) -> DistanceFn {
+    Arc::new(move |p| {
+        let (da, db) = (a(p), b(p));
+        if k <= 0.0 {
+            return f64::min(da, db);
+        }
+        let h = (0.5 + 0.5 * (db - da) / k).clamp(0.0, 1.0);
+
+        db + (da - db) * h - k * h * (1.0 - h)
+    })
+}
+
+// Removes `b` from `a`.
+pub fn subtraction(a: DistanceFn, b: DistanceFn) -> DistanceFn {
+    Arc::new(move |p| f64::max(a(p), -b(p)))
+}
+
+// Repeats `a` every `period` units along each axis; a zero component leaves
+// that axis unrepeated.
+pub fn repetition(a: DistanceFn, period: Vec3) -> DistanceFn {
+    let wrap = |x: f64, c: f64| {
+        if c > 0.0 {
+            x - c * f64::round(x / c)
+        } else {
+            x
+        }
+    };
+    Arc::new(move |p| {
+        a(Vec3::new(
+            wrap(p.x, period.x),
+            wrap(p.y, period.y),
+            wrap(p.z, period.z),
+        ))
+    })
+}
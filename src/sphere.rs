@@ -1,32 +1,62 @@
-use std::rc::Rc;
+use std::{f64::consts::PI, sync::Arc};
 
 use crate::{
+    aabb::Aabb,
     hittable::{HitRecord, Hittable},
     interval::Interval,
     material::Material,
     ray::Ray,
+    stats::{self, Primitive},
     vec3::Vec3,
 };
 
+// `center` is the position at time 0; a moving sphere travels along
+// `motion` to reach `center + motion` at time 1.
 pub struct Sphere {
     center: Vec3,
+    motion: Vec3,
     radius: f64,
-    mat: Rc<dyn Material>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
-    pub fn new(center: Vec3, radius: f64, mat: Rc<dyn Material>) -> Self {
+    pub fn new(center: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let radius = f64::max(0.0, radius);
+        let rvec = Vec3::new(radius, radius, radius);
         Sphere {
             center,
-            radius: f64::max(0.0, radius),
+            motion: Vec3::default(),
+            radius,
             mat,
+            bbox: Aabb::from_points(center - rvec, center + rvec),
         }
     }
+
+    pub fn moving(center1: Vec3, center2: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        let sphere = Sphere::new(center1, radius, mat);
+        let rvec = Vec3::new(sphere.radius, sphere.radius, sphere.radius);
+        let box2 = Aabb::from_points(center2 - rvec, center2 + rvec);
+        Sphere {
+            motion: center2 - center1,
+            bbox: Aabb::surrounding(&sphere.bbox, &box2),
+            ..sphere
+        }
+    }
+
+    pub fn get_sphere_uv(p: Vec3) -> (f64, f64) {
+        let theta = f64::acos(-p.y);
+        let phi = f64::atan2(-p.z, p.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
-        let oc = self.center - r.origin;
+        stats::count_intersection_test(Primitive::Sphere);
+        let center = self.center + r.time * self.motion;
+        let oc = center - r.origin;
         let a = r.dir.length_squared();
         let h = oc.dot(r.dir);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -46,10 +76,15 @@ impl Hittable for Sphere {
 
         rec.t = root;
         rec.p = r.at(rec.t);
-        let outward_normal = (rec.p - self.center) / self.radius;
+        let outward_normal = (rec.p - center) / self.radius;
         rec.set_face_normal(r, &outward_normal);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(outward_normal);
         rec.mat = self.mat.clone();
 
         true
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 }
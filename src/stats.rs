@@ -0,0 +1,187 @@
+use std::{
+    cell::Cell,
+    fmt::{self, Write},
+    ops::AddAssign,
+};
+
+// The kinds of primitive whose ray intersection tests are counted. Objects
+// that only combine or transform other objects are not counted themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Sphere,
+    Quad,
+    Disk,
+    Plane,
+    Cuboid,
+    Quadric,
+    Torus,
+    Heightfield,
+    Sdf,
+}
+
+impl Primitive {
+    pub const ALL: [Primitive; 9] = [
+        Primitive::Sphere,
+        Primitive::Quad,
+        Primitive::Disk,
+        Primitive::Plane,
+        Primitive::Cuboid,
+        Primitive::Quadric,
+        Primitive::Torus,
+        Primitive::Heightfield,
+        Primitive::Sdf,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Primitive::Sphere => "sphere",
+            Primitive::Quad => "quad",
+            Primitive::Disk => "disk",
+            Primitive::Plane => "plane",
+            Primitive::Cuboid => "cuboid",
+            Primitive::Quadric => "quadric",
+            Primitive::Torus => "torus",
+            Primitive::Heightfield => "heightfield",
+            Primitive::Sdf => "sdf",
+        }
+    }
+}
+
+// Intersection tests and BVH visits are counted where they happen, deep in
+// `Hittable::hit`, in counters private to each thread: plain increments, no
+// atomics or locks. The renderer collects them with `take_counters` after
+// each row.
+struct Counters {
+    intersection_tests: [Cell<u64>; Primitive::ALL.len()],
+    bvh_node_visits: Cell<u64>,
+}
+
+thread_local! {
+    static COUNTERS: Counters = const {
+        Counters {
+            intersection_tests: [const { Cell::new(0) }; Primitive::ALL.len()],
+            bvh_node_visits: Cell::new(0),
+        }
+    };
+}
+
+#[inline]
+pub(crate) fn count_intersection_test(primitive: Primitive) {
+    COUNTERS.with(|c| {
+        let counter = &c.intersection_tests[primitive as usize];
+        counter.set(counter.get() + 1);
+    });
+}
+
+#[inline]
+pub(crate) fn count_bvh_node_visit() {
+    COUNTERS.with(|c| c.bvh_node_visits.set(c.bvh_node_visits.get() + 1));
+}
+
+// Moves this thread's intersection and BVH counts into `stats`.
+pub(crate) fn take_counters(stats: &mut RenderStats) {
+    COUNTERS.with(|c| {
+        for (total, counter) in stats
+            .intersection_tests
+            .iter_mut()
+            .zip(&c.intersection_tests)
+        {
+            *total += counter.take();
+        }
+        stats.bvh_node_visits += c.bvh_node_visits.take();
+    });
+}
+
+// What a render did, for profiling scenes. Every camera ray starts a path;
+// `path_depth[n]` counts the paths of n segments, and each path ends by
+// escaping to the background, on a surface that does not scatter (such as
+// a light), or at the camera's `max_depth`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub primary_rays: u64,
+    pub secondary_rays: u64,
+    pub intersection_tests: [u64; Primitive::ALL.len()],
+    pub bvh_node_visits: u64,
+    pub path_depth: Vec<u64>,
+    pub escaped: u64,
+    pub absorbed: u64,
+    pub max_depth_reached: u64,
+}
+
+impl RenderStats {
+    pub fn rays(&self) -> u64 {
+        self.primary_rays + self.secondary_rays
+    }
+
+    pub fn intersection_tests(&self, primitive: Primitive) -> u64 {
+        self.intersection_tests[primitive as usize]
+    }
+
+    pub(crate) fn end_path(&mut self, depth: usize) {
+        if self.path_depth.len() <= depth {
+            self.path_depth.resize(depth + 1, 0);
+        }
+        self.path_depth[depth] += 1;
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        self.write_json(&mut json)
+            .expect("writing to a string cannot fail");
+        json
+    }
+
+    fn write_json(&self, out: &mut String) -> fmt::Result {
+        writeln!(out, "{{")?;
+        writeln!(out, "  \"primary_rays\": {},", self.primary_rays)?;
+        writeln!(out, "  \"secondary_rays\": {},", self.secondary_rays)?;
+        writeln!(out, "  \"intersection_tests\": {{")?;
+        for (n, primitive) in Primitive::ALL.iter().enumerate() {
+            let comma = if n + 1 < Primitive::ALL.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "    \"{}\": {}{comma}",
+                primitive.name(),
+                self.intersection_tests(*primitive)
+            )?;
+        }
+        writeln!(out, "  }},")?;
+        writeln!(out, "  \"bvh_node_visits\": {},", self.bvh_node_visits)?;
+        let depths: Vec<String> = self.path_depth.iter().map(u64::to_string).collect();
+        writeln!(out, "  \"path_depth\": [{}],", depths.join(", "))?;
+        writeln!(out, "  \"paths_ended\": {{")?;
+        writeln!(out, "    \"escaped\": {},", self.escaped)?;
+        writeln!(out, "    \"absorbed\": {},", self.absorbed)?;
+        writeln!(out, "    \"max_depth\": {}", self.max_depth_reached)?;
+        writeln!(out, "  }}")?;
+        writeln!(out, "}}")
+    }
+}
+
+impl AddAssign<&RenderStats> for RenderStats {
+    fn add_assign(&mut self, other: &RenderStats) {
+        self.primary_rays += other.primary_rays;
+        self.secondary_rays += other.secondary_rays;
+        for (total, count) in self
+            .intersection_tests
+            .iter_mut()
+            .zip(other.intersection_tests)
+        {
+            *total += count;
+        }
+        self.bvh_node_visits += other.bvh_node_visits;
+        if self.path_depth.len() < other.path_depth.len() {
+            self.path_depth.resize(other.path_depth.len(), 0);
+        }
+        for (total, count) in self.path_depth.iter_mut().zip(&other.path_depth) {
+            *total += count;
+        }
+        self.escaped += other.escaped;
+        self.absorbed += other.absorbed;
+        self.max_depth_reached += other.max_depth_reached;
+    }
+}
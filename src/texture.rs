@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use crate::{color::Color, image::Image, perlin::Perlin, vec3::Vec3};
+
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Color;
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        SolidColor { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: Vec3) -> Color {
+        self.albedo
+    }
+}
+
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        CheckerTexture {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Vec3) -> Color {
+        let x = f64::floor(self.inv_scale * p.x) as i64;
+        let y = f64::floor(self.inv_scale * p.y) as i64;
+        let z = f64::floor(self.inv_scale * p.z) as i64;
+
+        if (x + y + z) % 2 == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+pub struct ImageTexture {
+    image: Image,
+}
+
+impl ImageTexture {
+    pub fn new(image: Image) -> Self {
+        ImageTexture { image }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Vec3) -> Color {
+        if self.image.width() == 0 || self.image.height() == 0 {
+            return Color::new(0.0, 1.0, 1.0);
+        }
+
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        let i = (u * self.image.width() as f64) as usize;
+        let j = (v * self.image.height() as f64) as usize;
+        let pixel = self.image.pixel(i, j);
+
+        // Image files are gamma encoded; undo the same gamma-2 curve that
+        // `write_color` applies on output.
+        pixel.elementwise_mul(pixel)
+    }
+}
+
+pub struct NoiseTexture {
+    noise: Perlin,
+    scale: f64,
+}
+
+impl NoiseTexture {
+    pub fn new(scale: f64) -> Self {
+        NoiseTexture {
+            noise: Perlin::new(),
+            scale,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    // Marble: turbulence shifts the phase of a sine wave along z.
+    fn value(&self, _u: f64, _v: f64, p: Vec3) -> Color {
+        Color::new(0.5, 0.5, 0.5)
+            * (1.0 + f64::sin(self.scale * p.z + 10.0 * self.noise.turb(p, 7)))
+    }
+}
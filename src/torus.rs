@@ -0,0 +1,133 @@
+use std::{f64::consts::PI, sync::Arc};
+
+use crate::{
+    aabb::{self, Aabb},
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    material::Material,
+    onb::Onb,
+    polynomial,
+    ray::Ray,
+    stats::{self, Primitive},
+    vec3::Vec3,
+};
+
+pub struct Torus {
+    center: Vec3,
+    basis: Onb,
+    major_radius: f64,
+    minor_radius: f64,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: f64,
+        minor_radius: f64,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let basis = Onb::new(axis);
+        let major_radius = f64::max(0.0, major_radius);
+        let minor_radius = f64::max(0.0, minor_radius);
+
+        let extent = major_radius + minor_radius;
+        let local = Aabb::from_points(
+            Vec3::new(-extent, -extent, -minor_radius),
+            Vec3::new(extent, extent, minor_radius),
+        );
+        let mut bbox = aabb::EMPTY;
+        for corner in local.corners() {
+            let p = center + basis.to_world(corner);
+            bbox = Aabb::surrounding(&bbox, &Aabb::from_points(p, p));
+        }
+
+        Torus {
+            center,
+            basis,
+            major_radius,
+            minor_radius,
+            mat,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        stats::count_intersection_test(Primitive::Torus);
+        let dir_length = r.dir.length();
+        if dir_length == 0.0 {
+            return false;
+        }
+
+        // Work with a unit direction in the torus frame so the distance along
+        // the ray is measured in world units.
+        let origin = self.basis.to_local(r.origin - self.center);
+        let dir = self.basis.to_local(r.dir / dir_length);
+
+        // Clip against the bounding sphere first and restart the ray at its
+        // entry point. Keeping the origin close to the surface keeps the
+        // quartic's coefficients small, which is what makes grazing and
+        // far-away rays stable.
+        let bound = self.major_radius + self.minor_radius;
+        let h = origin.dot(dir);
+        let discriminant = h * h - (origin.length_squared() - bound * bound);
+        if discriminant < 0.0 {
+            return false;
+        }
+        let sqrtd = f64::sqrt(discriminant);
+        let s_min = f64::max(-h - sqrtd, interval.min * dir_length);
+        let s_max = f64::min(-h + sqrtd, interval.max * dir_length);
+        if s_min >= s_max {
+            return false;
+        }
+
+        let o = origin + s_min * dir;
+        let major_squared = self.major_radius * self.major_radius;
+        let minor_squared = self.minor_radius * self.minor_radius;
+        let g = o.length_squared() + major_squared - minor_squared;
+        let h = o.dot(dir);
+        let planar = dir.x * dir.x + dir.y * dir.y;
+        let coeffs = [
+            g * g - 4.0 * major_squared * (o.x * o.x + o.y * o.y),
+            4.0 * h * g - 8.0 * major_squared * (o.x * dir.x + o.y * dir.y),
+            4.0 * h * h + 2.0 * g - 4.0 * major_squared * planar,
+            4.0 * h,
+            1.0,
+        ];
+
+        let roots = polynomial::find_roots(&coeffs, Interval::new(0.0, s_max - s_min));
+        let Some(t) = roots
+            .into_iter()
+            .map(|s| (s + s_min) / dir_length)
+            .find(|&t| interval.surrounds(t))
+        else {
+            return false;
+        };
+
+        let p = origin + (t * dir_length) * dir;
+        let k = p.length_squared() - major_squared - minor_squared;
+        let normal = Vec3::new(p.x * k, p.y * k, p.z * (k + 2.0 * major_squared));
+
+        let phi = f64::atan2(p.y, p.x) + PI;
+        let ring = f64::sqrt(p.x * p.x + p.y * p.y) - self.major_radius;
+        let theta = f64::atan2(p.z, ring) + PI;
+
+        rec.t = t;
+        rec.p = r.at(t);
+        rec.u = phi / (2.0 * PI);
+        rec.v = theta / (2.0 * PI);
+        let outward_normal = Vec3::unit_vector(self.basis.to_world(normal));
+        rec.set_face_normal(r, &outward_normal);
+        rec.mat = self.mat.clone();
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
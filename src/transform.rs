@@ -0,0 +1,227 @@
+use std::sync::Arc;
+
+use crate::{
+    aabb::{self, Aabb},
+    hittable::{HitRecord, Hittable},
+    interval::Interval,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Mat3 {
+    rows: [Vec3; 3],
+}
+
+impl Mat3 {
+    const IDENTITY: Mat3 = Mat3 {
+        rows: [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ],
+    };
+
+    fn column(&self, n: usize) -> Vec3 {
+        let pick = |v: Vec3| match n {
+            0 => v.x,
+            1 => v.y,
+            _ => v.z,
+        };
+        Vec3::new(pick(self.rows[0]), pick(self.rows[1]), pick(self.rows[2]))
+    }
+
+    fn transpose(&self) -> Mat3 {
+        Mat3 {
+            rows: [self.column(0), self.column(1), self.column(2)],
+        }
+    }
+
+    fn mul_vec(&self, v: Vec3) -> Vec3 {
+        Vec3::new(
+            self.rows[0].dot(v),
+            self.rows[1].dot(v),
+            self.rows[2].dot(v),
+        )
+    }
+
+    fn mul(&self, other: &Mat3) -> Mat3 {
+        let columns = [other.column(0), other.column(1), other.column(2)];
+        let row = |r: Vec3| Vec3::new(r.dot(columns[0]), r.dot(columns[1]), r.dot(columns[2]));
+        Mat3 {
+            rows: [row(self.rows[0]), row(self.rows[1]), row(self.rows[2])],
+        }
+    }
+
+    // `None` for a singular matrix, such as a scale by zero.
+    fn inverse(&self) -> Option<Mat3> {
+        let [a, b, c] = self.rows;
+        let det = a.dot(b.cross(c));
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let adjugate_transpose = Mat3 {
+            rows: [b.cross(c), c.cross(a), a.cross(b)],
+        };
+        let mut inverse = adjugate_transpose.transpose();
+        for row in inverse.rows.iter_mut() {
+            *row /= det;
+        }
+
+        Some(inverse)
+    }
+
+    fn rotation(axis: Vec3, degrees: f64) -> Mat3 {
+        let a = Vec3::unit_vector(axis);
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = 1.0 - cos;
+
+        Mat3 {
+            rows: [
+                Vec3::new(
+                    cos + a.x * a.x * k,
+                    a.x * a.y * k - a.z * sin,
+                    a.x * a.z * k + a.y * sin,
+                ),
+                Vec3::new(
+                    a.y * a.x * k + a.z * sin,
+                    cos + a.y * a.y * k,
+                    a.y * a.z * k - a.x * sin,
+                ),
+                Vec3::new(
+                    a.z * a.x * k - a.y * sin,
+                    a.z * a.y * k + a.x * sin,
+                    cos + a.z * a.z * k,
+                ),
+            ],
+        }
+    }
+
+    fn scale(s: Vec3) -> Mat3 {
+        Mat3 {
+            rows: [
+                Vec3::new(s.x, 0.0, 0.0),
+                Vec3::new(0.0, s.y, 0.0),
+                Vec3::new(0.0, 0.0, s.z),
+            ],
+        }
+    }
+}
+
+// Places an object in the world through an affine transform. Operations are
+// applied in the order the builder methods are called, so
+// `Transform::new(obj).rotate_y(30.0).translate(v)` rotates first and then
+// moves the rotated object.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    linear: Mat3,
+    // `None` once the transform has flattened the object, which then
+    // cannot be hit.
+    inverse: Option<Mat3>,
+    offset: Vec3,
+    bbox: Aabb,
+}
+
+impl Transform {
+    pub fn new(object: Arc<dyn Hittable>) -> Self {
+        let bbox = object.bounding_box();
+        Transform {
+            object,
+            linear: Mat3::IDENTITY,
+            inverse: Some(Mat3::IDENTITY),
+            offset: Vec3::default(),
+            bbox,
+        }
+    }
+
+    pub fn translate(mut self, offset: Vec3) -> Self {
+        self.offset += offset;
+        self.update_bbox();
+        self
+    }
+
+    pub fn rotate(self, axis: Vec3, degrees: f64) -> Self {
+        self.apply(Mat3::rotation(axis, degrees))
+    }
+
+    pub fn rotate_x(self, degrees: f64) -> Self {
+        self.rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    pub fn rotate_y(self, degrees: f64) -> Self {
+        self.rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    pub fn rotate_z(self, degrees: f64) -> Self {
+        self.rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    pub fn scale(self, factors: Vec3) -> Self {
+        self.apply(Mat3::scale(factors))
+    }
+
+    fn apply(mut self, m: Mat3) -> Self {
+        self.linear = m.mul(&self.linear);
+        self.inverse = self.linear.inverse();
+        self.offset = m.mul_vec(self.offset);
+        self.update_bbox();
+        self
+    }
+
+    // Whether the transform can be undone. A zero scale factor or rotation
+    // axis, or scales so small or large that the matrix degenerates in
+    // floating point, leave the object flat and invisible.
+    pub fn is_invertible(&self) -> bool {
+        self.inverse.is_some()
+    }
+
+    fn update_bbox(&mut self) {
+        if self.inverse.is_none() {
+            self.bbox = aabb::EMPTY;
+            return;
+        }
+        let object_bbox = self.object.bounding_box();
+        let infinite = [object_bbox.x, object_bbox.y, object_bbox.z]
+            .iter()
+            .any(|i| i.min.is_infinite() || i.max.is_infinite());
+        if infinite {
+            self.bbox = aabb::UNIVERSE;
+            return;
+        }
+
+        let mut bbox = aabb::EMPTY;
+        for corner in object_bbox.corners() {
+            let p = self.linear.mul_vec(corner) + self.offset;
+            bbox = Aabb::surrounding(&bbox, &Aabb::from_points(p, p));
+        }
+        self.bbox = bbox;
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, interval: Interval, rec: &mut HitRecord) -> bool {
+        // The direction is transformed without renormalising, so the ray
+        // parameter means the same thing in both spaces.
+        let Some(inverse) = &self.inverse else {
+            return false;
+        };
+        let local = Ray::new(
+            inverse.mul_vec(r.origin - self.offset),
+            inverse.mul_vec(r.dir),
+            r.time,
+        );
+        if !self.object.hit(&local, interval, rec) {
+            return false;
+        }
+
+        rec.p = r.at(rec.t);
+        rec.normal = Vec3::unit_vector(inverse.transpose().mul_vec(rec.normal));
+
+        true
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -1,4 +1,3 @@
-
 pub fn random_double() -> f64 {
     fastrand::f64()
 }
@@ -10,3 +9,15 @@ pub fn random_double_range(min: f64, max: f64) -> f64 {
 pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * std::f64::consts::PI / 180.0
 }
+
+pub fn seed(seed: u64) {
+    fastrand::seed(seed);
+}
+
+// Derives an independent stream seed from a base seed and an index.
+pub fn mix_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed ^ index.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}